@@ -0,0 +1,319 @@
+//! Signing core of [atlassian-app-auth], for targets without `std`.
+//!
+//! This crate creates the query string hash and the HS256 JWT for a
+//! request to an Atlassian API, using only `alloc`. It has no clock, so
+//! the caller passes the issue time of each token; it also has no URL
+//! type, so requests are passed as a method, path, and decoded query
+//! pairs.
+//!
+//! Most apps should use [atlassian-app-auth] instead, which builds on
+//! this crate. To build without `std`, disable the default features:
+//!
+//! ```toml
+//! atlassian-app-auth-core = { version = "1.0.2", default-features = false }
+//! ```
+//!
+//! ```
+//! use atlassian_app_auth_core::{
+//!     canonical_request, encode_token, query_string_hash, Claims,
+//! };
+//!
+//! let query = [("jql", "project = X")];
+//! let canonical = canonical_request("GET", "/rest/api/3/search", &query);
+//! let qsh = query_string_hash(&canonical);
+//! let claims = Claims {
+//!     iss: "my-app",
+//!     qsh: &qsh,
+//!     iat: 1_600_000_000,
+//!     exp: 1_600_000_300,
+//!     nbf: None,
+//! };
+//! let token = encode_token(&claims, b"secret");
+//! let header = format!("JWT {}", token);
+//! ```
+//!
+//! [atlassian-app-auth]: https://docs.rs/atlassian-app-auth
+
+#![no_std]
+#![warn(missing_docs)]
+
+extern crate alloc;
+#[cfg(feature = "std")]
+extern crate std;
+
+use alloc::collections::BTreeMap;
+use alloc::format;
+use alloc::string::{String, ToString};
+use alloc::vec::Vec;
+use core::fmt;
+use hmac::{Hmac, Mac, NewMac};
+use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// The set of characters to percent-encode for query parameters. The
+/// Jira documentation says these should be consistent with OAuth 1.0,
+/// which is defined in RFC 5849.
+///
+/// From <https://tools.ietf.org/html/rfc5849#page-29>:
+/// * (ALPHA, DIGIT, "-", ".", "_", "~") MUST NOT be encoded
+/// * All other characters MUST be encoded.
+pub const QUERY_PARAM_ENCODE_SET: &AsciiSet = &CONTROLS
+    .add(b' ')
+    .add(b'!')
+    .add(b'"')
+    .add(b'#')
+    .add(b'$')
+    .add(b'%')
+    .add(b'&')
+    .add(b'\'')
+    .add(b'(')
+    .add(b')')
+    .add(b'*')
+    .add(b'+')
+    .add(b',')
+    .add(b'/')
+    .add(b':')
+    .add(b';')
+    .add(b'<')
+    .add(b'=')
+    .add(b'>')
+    .add(b'?')
+    .add(b'@')
+    .add(b'[')
+    .add(b'\\')
+    .add(b']')
+    .add(b'^')
+    .add(b'`')
+    .add(b'{')
+    .add(b'|')
+    .add(b'}');
+
+/// Errors returned by this crate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The HTTP method is not a valid RFC 7230 token.
+    InvalidMethod {
+        /// The invalid method.
+        method: String,
+    },
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Error::InvalidMethod { method } => {
+                write!(f, "invalid HTTP method: {:?}", method)
+            }
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl std::error::Error for Error {}
+
+/// Check that `method` is a valid HTTP method token, as defined in
+/// RFC 7230.
+pub fn validate_method(method: &str) -> Result<(), Error> {
+    let is_token_char =
+        |c: char| c.is_ascii_alphanumeric() || "!#$%&'*+-.^_`|~".contains(c);
+    if method.is_empty() || !method.chars().all(is_token_char) {
+        return Err(Error::InvalidMethod {
+            method: method.into(),
+        });
+    }
+    Ok(())
+}
+
+/// Create the canonical request that the query string hash is computed
+/// from.
+///
+/// `path` is the request path without the query string, and is assumed
+/// to already be canonical. `query` holds the query parameters as
+/// decoded (not percent-encoded) key/value pairs, in any order.
+///
+/// As in Atlassian's implementation, keys and values are
+/// percent-encoded, and the parameters are sorted by encoded key. The
+/// values of a repeated key are sorted and joined with commas, so
+/// `a=2&a=1` becomes `a=1,2`. The `jwt` parameter, which carries the
+/// token itself on requests from Atlassian, is left out.
+// TODO: there are quite a few special cases described in Atlassian's
+// documentation that are not yet handled here.
+pub fn canonical_request<K, V>(
+    method: &str,
+    path: &str,
+    query: &[(K, V)],
+) -> String
+where
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    let method = method.to_uppercase();
+    let encode =
+        |s: &str| utf8_percent_encode(s, QUERY_PARAM_ENCODE_SET).to_string();
+
+    // Encoded key to encoded values.
+    let mut params: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (key, val) in query {
+        if key.as_ref() == "jwt" {
+            continue;
+        }
+        params
+            .entry(encode(key.as_ref()))
+            .or_default()
+            .push(encode(val.as_ref()));
+    }
+
+    let query_pairs = params
+        .into_iter()
+        .map(|(key, mut vals)| {
+            vals.sort_unstable();
+            format!("{}={}", key, vals.join(","))
+        })
+        .collect::<Vec<_>>();
+
+    format!("{}&{}&{}", method, path, query_pairs.join("&"))
+}
+
+/// Hash a canonical request (see [`canonical_request`]) to get the
+/// query string hash ("qsh" claim), as lowercase hex.
+pub fn query_string_hash(canonical_request: &str) -> String {
+    format!("{:x}", Sha256::digest(canonical_request.as_bytes()))
+}
+
+/// Claims of a Connect JWT. Times are seconds since the Unix epoch.
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Claims<'a> {
+    /// The issuer of the claim. This matches the key in the app
+    /// descriptor (e.g. "com.example.app").
+    pub iss: &'a str,
+
+    /// Query string hash of the request (see [`query_string_hash`]).
+    pub qsh: &'a str,
+
+    /// The time that this JWT was issued.
+    pub iat: u64,
+
+    /// JWT expiration time.
+    pub exp: u64,
+
+    /// Time before which the JWT must not be accepted.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nbf: Option<u64>,
+}
+
+/// Encoded JOSE header of every token: `{"typ":"JWT","alg":"HS256"}`.
+const ENCODED_HEADER: &str = "eyJ0eXAiOiJKV1QiLCJhbGciOiJIUzI1NiJ9";
+
+/// Encode `claims` as a JWT signed with HMAC-SHA256 using
+/// `shared_secret`.
+pub fn encode_token(claims: &Claims, shared_secret: &[u8]) -> String {
+    let claims =
+        serde_json::to_vec(claims).expect("claims always serialize to JSON");
+    let message = format!(
+        "{}.{}",
+        ENCODED_HEADER,
+        base64::encode_config(claims, base64::URL_SAFE_NO_PAD)
+    );
+
+    let mut mac = Hmac::<Sha256>::new_from_slice(shared_secret)
+        .expect("HMAC accepts keys of any length");
+    mac.update(message.as_bytes());
+    let signature = mac.finalize().into_bytes();
+
+    format!(
+        "{}.{}",
+        message,
+        base64::encode_config(signature, base64::URL_SAFE_NO_PAD)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_canonical_request() {
+        assert_eq!(
+            canonical_request(
+                "get",
+                "/example",
+                &[("query", "x y,z+*~"), ("a", "1")]
+            ),
+            "GET&/example&a=1&query=x%20y%2Cz%2B%2A~"
+        );
+    }
+
+    #[test]
+    fn test_canonical_request_repeated_keys() {
+        assert_eq!(
+            canonical_request(
+                "GET",
+                "/example",
+                &[("type", "system"), ("b", "1"), ("type", "custom,x")]
+            ),
+            "GET&/example&b=1&type=custom%2Cx,system"
+        );
+    }
+
+    #[test]
+    fn test_canonical_request_key_order() {
+        // Sorted by key, not by "key=value": `a` comes before `a-b`
+        // even though `-` sorts before `=`.
+        assert_eq!(
+            canonical_request(
+                "GET",
+                "/example",
+                &[("a-b", "1"), ("a", "2"), ("a b", "3")]
+            ),
+            "GET&/example&a=2&a%20b=3&a-b=1"
+        );
+    }
+
+    #[test]
+    fn test_canonical_request_jwt() {
+        assert_eq!(
+            canonical_request("GET", "/page", &[("jwt", "x"), ("a", "1")]),
+            "GET&/page&a=1"
+        );
+    }
+
+    #[test]
+    fn test_query_string_hash() {
+        assert_eq!(
+            query_string_hash("GET&/example&"),
+            "0073e2edb5df6a8af18c4398d32532f2b46a05295d10fac402131dd044032a61"
+        );
+    }
+
+    #[test]
+    fn test_validate_method() {
+        validate_method("get").unwrap();
+        assert_eq!(
+            validate_method("GET /"),
+            Err(Error::InvalidMethod {
+                method: "GET /".into()
+            })
+        );
+    }
+
+    #[test]
+    fn test_encode_token() {
+        let claims = Claims {
+            iss: "app",
+            qsh: "abc",
+            iat: 1,
+            exp: 2,
+            nbf: None,
+        };
+        let token = encode_token(&claims, b"secret");
+        let parts: Vec<&str> = token.split('.').collect();
+        assert_eq!(parts[0], ENCODED_HEADER);
+        assert_eq!(
+            base64::decode_config(parts[1], base64::URL_SAFE_NO_PAD).unwrap(),
+            br#"{"iss":"app","qsh":"abc","iat":1,"exp":2}"#
+        );
+        assert_eq!(parts.len(), 3);
+    }
+}
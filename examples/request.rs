@@ -1,17 +1,85 @@
+use argh::FromArgs;
+use atlassian_app_auth::{Credentials, RetryPolicy};
 use reqwest::blocking::Client;
-use reqwest::Method;
-use serde::Deserialize;
-use std::fs;
-use std::path::PathBuf;
+use reqwest::{Method, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs::{self, OpenOptions};
+use std::io::{self, BufRead, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
 use std::time::Duration;
+use url::Url;
 
-// Example of running this example:
+// Examples of running this example:
 //
+// cargo run --example request -- send <creds-path> <method> <url>
 // cargo run --example request -- <creds-path> <method> <url>
+// cargo run --example request -- send --watch 5m --changes-only \
+//     <creds-path> get <url>
+// cargo run --example request -- repl <creds-path> <base-url>
+// cargo run --example request -- openapi <spec> <creds-path> <base-url> \
+//     getIssue -p issueIdOrKey=KEY-1
+// cargo run --example request -- creds doctor <creds-path> <base-url>
+// cargo run --example request -- --record fixtures send <creds-path> get <url>
+// cargo run --example request -- --replay fixtures send <creds-path> get <url>
+// cargo run --example request -- --error-format json send <creds-path> get <url>
+// cargo run --example request -- --output ndjson send <creds-path> get \
+//     '<base-url>/rest/api/3/search?jql=project=KEY'
 
-/// Send a request to Jira and pretty-print the JSON response.
+/// Send requests to Jira and pretty-print the JSON responses.
 #[derive(argh::FromArgs)]
 struct Opt {
+    /// maximum number of attempts for requests that fail to connect,
+    /// time out, or get a 429 or 5xx gateway response (default: 3)
+    #[argh(option, default = "RetryPolicy::DEFAULT_MAX_ATTEMPTS")]
+    attempts: u32,
+
+    /// save each request and its response to a file in this directory
+    #[argh(option)]
+    record: Option<PathBuf>,
+
+    /// answer requests with responses saved by --record in this
+    /// directory instead of sending them. Requests are still signed.
+    #[argh(option)]
+    replay: Option<PathBuf>,
+
+    /// how to print errors: "text" (default), or "json" for one JSON
+    /// object per error on stderr
+    #[argh(
+        option,
+        default = "ErrorFormat::Text",
+        from_str_fn(parse_error_format)
+    )]
+    error_format: ErrorFormat,
+
+    /// how to print responses: "pretty" (default) for pretty-printed
+    /// JSON, or "ndjson" for one JSON value per line. With ndjson, the
+    /// items of a paginated GET response are printed as each page
+    /// arrives, and the following pages are fetched until the last
+    /// one. --watch always pretty-prints.
+    #[argh(option, default = "Output::Pretty", from_str_fn(parse_output))]
+    output: Output,
+
+    #[argh(subcommand)]
+    command: Command,
+}
+
+#[derive(argh::FromArgs)]
+#[argh(subcommand)]
+enum Command {
+    Send(SendOpt),
+    Repl(ReplOpt),
+    OpenApi(OpenApiOpt),
+    Creds(CredsOpt),
+}
+
+/// Send one request.
+#[derive(argh::FromArgs)]
+#[argh(subcommand, name = "send")]
+struct SendOpt {
     /// optional path of a JSON file to include as the body of the request
     #[argh(option)]
     body: Option<PathBuf>,
@@ -28,78 +96,1169 @@ struct Opt {
     /// url such as https://mycorp.atlassian.net/rest/api/3/project/search?query=KEY
     #[argh(positional)]
     url: String,
+
+    /// repeat the request at this interval, such as "30s" or "5m",
+    /// until interrupted
+    #[argh(option, from_str_fn(parse_interval))]
+    watch: Option<Duration>,
+
+    /// with --watch, only print responses that differ from the
+    /// previous one
+    #[argh(switch)]
+    changes_only: bool,
 }
 
-// TODO: add a way to send a JSON body
+/// Interactively send requests to one site.
+#[derive(argh::FromArgs)]
+#[argh(subcommand, name = "repl")]
+struct ReplOpt {
+    /// optional file to load command history from and append it to
+    #[argh(option)]
+    history: Option<PathBuf>,
+
+    /// path of the JSON credentials file containing the key and
+    /// secret key
+    #[argh(positional)]
+    creds: PathBuf,
 
-#[derive(Deserialize)]
-struct Creds {
-    key: String,
-    secret: String,
+    /// base url of the site, such as https://mycorp.atlassian.net
+    #[argh(positional)]
+    base_url: String,
 }
 
-fn main() {
-    let opt: Opt = argh::from_env();
-
-    // Read the credentials
-    let creds_raw =
-        fs::read_to_string(opt.creds).expect("failed to read creds file");
-    let creds: Creds =
-        serde_json::from_str(&creds_raw).expect("failed to parse creds file");
-
-    // Create the request
-    let client = Client::new();
-    let method = Method::from_bytes(opt.method.to_uppercase().as_bytes())
-        .expect("invalid method");
-    let mut request = client.request(method, &opt.url);
-
-    // Add the optional JSON body
-    if let Some(body_path) = opt.body {
-        let body =
-            fs::read_to_string(body_path).expect("failed to read body file");
-        request = request
-            .header("Content-Type", "application/json")
-            .body(body);
-    }
-
-    let mut request = request.build().expect("invalid request");
-
-    // Add the auth header
-    let header = atlassian_app_auth::create_auth_header(
-        &atlassian_app_auth::Parameters {
-            method: request.method().as_str().into(),
-            url: request.url().clone(),
-            valid_for: Duration::from_secs(30),
-            app_key: creds.key.clone(),
-            shared_secret: creds.secret.clone(),
-        },
-    )
-    .expect("failed to create auth header");
-    request.headers_mut().insert(
-        header.name,
-        header.value.parse().expect("failed to parse auth value"),
-    );
+/// Send requests described by an OpenAPI spec, such as the Jira Cloud
+/// spec at https://developer.atlassian.com/cloud/jira/platform/swagger-v3.v3.json
+///
+/// Without an operation, list the operations in the spec.
+#[derive(argh::FromArgs)]
+#[argh(subcommand, name = "openapi")]
+struct OpenApiOpt {
+    /// optional path of a JSON file to include as the body of the request
+    #[argh(option)]
+    body: Option<PathBuf>,
+
+    /// parameter of the operation as name=value, can be repeated
+    #[argh(option, short = 'p')]
+    param: Vec<String>,
+
+    /// path or url of the OpenAPI spec in JSON format
+    #[argh(positional)]
+    spec: String,
+
+    /// path of the JSON credentials file containing the key and
+    /// secret key
+    #[argh(positional)]
+    creds: PathBuf,
+
+    /// base url of the site, such as https://mycorp.atlassian.net
+    #[argh(positional)]
+    base_url: String,
+
+    /// operation ID such as "getIssue"
+    #[argh(positional)]
+    operation: Option<String>,
+}
+
+/// Manage credentials files.
+#[derive(argh::FromArgs)]
+#[argh(subcommand, name = "creds")]
+struct CredsOpt {
+    #[argh(subcommand)]
+    command: CredsCommand,
+}
+
+#[derive(argh::FromArgs)]
+#[argh(subcommand)]
+enum CredsCommand {
+    Doctor(DoctorOpt),
+}
+
+/// Check that a credentials file works, reporting the first step that
+/// fails.
+#[derive(argh::FromArgs)]
+#[argh(subcommand, name = "doctor")]
+struct DoctorOpt {
+    /// path to request, relative to the base url (default:
+    /// /rest/api/3/myself)
+    #[argh(option, default = "String::from(\"/rest/api/3/myself\")")]
+    endpoint: String,
+
+    /// path of the JSON credentials file containing the key and
+    /// secret key
+    #[argh(positional)]
+    creds: PathBuf,
+
+    /// base url of the site, such as https://mycorp.atlassian.net
+    #[argh(positional)]
+    base_url: String,
+}
 
-    // Send the request and print the response
-    let resp = client.execute(request).expect("failed to send request");
-    match resp.error_for_status_ref() {
-        Ok(_) => {
-            // Pretty-print the response
-            let resp: serde_json::Value =
-                resp.json().expect("failed to parse response");
-            println!(
+const REPL_HELP: &str = "\
+Commands:
+  <method> <path> [json]  send a request, e.g. `get /rest/api/3/myself`
+  history                 list previous commands
+  !<n>                    run command <n> from the history again
+  help                    show this message
+  quit                    exit (or press Ctrl-D)";
+
+/// Options that apply to every request.
+struct Settings {
+    attempts: u32,
+    fixtures: Fixtures,
+    error_format: ErrorFormat,
+    output: Output,
+}
+
+impl Settings {
+    /// Print `failure` on stderr.
+    fn report(&self, failure: &Failure) {
+        match self.error_format {
+            ErrorFormat::Text => eprintln!("{}", failure),
+            ErrorFormat::Json => eprintln!(
                 "{}",
-                serde_json::to_string_pretty(&resp)
-                    .expect("failed to format response")
+                serde_json::to_string(failure)
+                    .expect("failed to serialize error")
+            ),
+        }
+    }
+
+    /// Print `failure` on stderr and exit.
+    fn fail(&self, failure: Failure) -> ! {
+        self.report(&failure);
+        std::process::exit(1);
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum ErrorFormat {
+    Text,
+    Json,
+}
+
+fn parse_error_format(value: &str) -> Result<ErrorFormat, String> {
+    match value {
+        "text" => Ok(ErrorFormat::Text),
+        "json" => Ok(ErrorFormat::Json),
+        _ => Err(format!("expected \"text\" or \"json\": {}", value)),
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Output {
+    Pretty,
+    Ndjson,
+}
+
+fn parse_output(value: &str) -> Result<Output, String> {
+    match value {
+        "pretty" => Ok(Output::Pretty),
+        "ndjson" => Ok(Output::Ndjson),
+        _ => Err(format!("expected \"pretty\" or \"ndjson\": {}", value)),
+    }
+}
+
+/// Why a request failed.
+#[derive(Serialize)]
+struct Failure {
+    /// Step that failed: "arguments", "credentials", "spec", "history",
+    /// "sign", "network", "fixture", or "http". `creds doctor` uses the
+    /// names of its steps instead.
+    stage: &'static str,
+    message: String,
+    /// Status of the response, for "http" failures.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    status: Option<u16>,
+    /// Body of the response, for "http" failures. Atlassian's JSON
+    /// error bodies are included as JSON rather than a string.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    body: Option<Value>,
+}
+
+impl Failure {
+    fn new(stage: &'static str, message: impl ToString) -> Failure {
+        Failure {
+            stage,
+            message: message.to_string(),
+            status: None,
+            body: None,
+        }
+    }
+}
+
+impl std::fmt::Display for Failure {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.body {
+            Some(Value::String(body)) => {
+                write!(f, "request failed: {}, body: {}", self.message, body)
+            }
+            Some(body) => {
+                write!(f, "request failed: {}, body: {}", self.message, body)
+            }
+            None => write!(f, "error ({}): {}", self.stage, self.message),
+        }
+    }
+}
+
+/// Where responses come from.
+enum Fixtures {
+    /// Send requests over the network.
+    Network,
+    /// Send requests over the network and save them in a directory.
+    Record(PathBuf),
+    /// Read responses saved by `Record` from a directory.
+    Replay(PathBuf),
+}
+
+/// Status and body of a response.
+#[derive(Deserialize, Serialize)]
+struct Reply {
+    status: u16,
+    body: String,
+}
+
+/// Request and response saved by `--record`. Headers, including the
+/// auth header, are not saved, and the URL has any username or
+/// password removed.
+#[derive(Deserialize, Serialize)]
+struct Fixture {
+    method: String,
+    url: String,
+    body: Option<String>,
+    response: Reply,
+}
+
+impl Fixture {
+    /// Path of the fixture for a request in `dir`.
+    fn path(
+        dir: &Path,
+        method: &str,
+        url: &Url,
+        body: Option<&str>,
+    ) -> PathBuf {
+        let mut hasher = Sha256::new();
+        hasher.update(format!("{} {}\n", method, url));
+        hasher.update(body.unwrap_or_default());
+        let hash = format!("{:x}", hasher.finalize());
+        dir.join(format!("{}-{}.json", method.to_lowercase(), &hash[..16]))
+    }
+
+    fn load(
+        dir: &Path,
+        method: &str,
+        url: &Url,
+        body: Option<&str>,
+    ) -> Result<Reply, Failure> {
+        let url = sanitize(url);
+        let path = Fixture::path(dir, method, &url, body);
+        let json = fs::read_to_string(&path).map_err(|err| {
+            Failure::new(
+                "fixture",
+                format!(
+                    "no recorded response for {} {} ({}): {}",
+                    method,
+                    url,
+                    path.display(),
+                    err
+                ),
+            )
+        })?;
+        let fixture: Fixture = serde_json::from_str(&json).map_err(|err| {
+            Failure::new(
+                "fixture",
+                format!("invalid {}: {}", path.display(), err),
+            )
+        })?;
+        Ok(fixture.response)
+    }
+
+    fn save(
+        dir: &Path,
+        method: &str,
+        url: &Url,
+        body: Option<&str>,
+        reply: &Reply,
+    ) -> Result<(), Failure> {
+        let url = sanitize(url);
+        let path = Fixture::path(dir, method, &url, body);
+        let fixture = Fixture {
+            method: method.into(),
+            url: url.into(),
+            body: body.map(String::from),
+            response: Reply {
+                status: reply.status,
+                body: reply.body.clone(),
+            },
+        };
+        let json = serde_json::to_string_pretty(&fixture)
+            .expect("failed to serialize fixture");
+        fs::create_dir_all(dir)
+            .and_then(|()| fs::write(&path, json))
+            .map_err(|err| {
+                Failure::new(
+                    "fixture",
+                    format!("failed to write {}: {}", path.display(), err),
+                )
+            })
+    }
+}
+
+/// Remove the username and password from `url`.
+fn sanitize(url: &Url) -> Url {
+    let mut url = url.clone();
+    // These only fail for URLs that can't have a username or password.
+    let _ = url.set_username("");
+    let _ = url.set_password(None);
+    url
+}
+
+/// Credentials, HTTP client, and retry policy, loaded once and used
+/// for every request.
+struct Session<'a> {
+    creds: Credentials,
+    client: Client,
+    policy: RetryPolicy,
+    fixtures: &'a Fixtures,
+    output: Output,
+}
+
+impl<'a> Session<'a> {
+    fn new(creds: PathBuf, settings: &'a Settings) -> Session<'a> {
+        Session {
+            creds: Credentials::from_json_file(creds).unwrap_or_else(|err| {
+                settings.fail(Failure::new("credentials", err))
+            }),
+            client: Client::new(),
+            policy: RetryPolicy::new().with_max_attempts(settings.attempts),
+            fixtures: &settings.fixtures,
+            output: settings.output,
+        }
+    }
+
+    /// Send a request and print the response as chosen with
+    /// `--output`.
+    fn print(
+        &self,
+        method: &str,
+        url: &str,
+        body: Option<&str>,
+    ) -> Result<(), Failure> {
+        match self.output {
+            Output::Pretty => {
+                print_output(format_response(self.send(method, url, body)?)?);
+                Ok(())
+            }
+            Output::Ndjson => self.stream(method, url, body),
+        }
+    }
+
+    /// Print a response as NDJSON. If it's a page of results, print
+    /// each item on its own line, then fetch and print the following
+    /// pages.
+    fn stream(
+        &self,
+        method: &str,
+        url: &str,
+        body: Option<&str>,
+    ) -> Result<(), Failure> {
+        let mut url = Url::parse(url).map_err(|err| {
+            Failure::new("arguments", format!("invalid url: {}", err))
+        })?;
+        loop {
+            let body = check_status(self.send(method, url.as_str(), body)?)?;
+            if body.is_empty() {
+                return Ok(());
+            }
+            let value =
+                serde_json::from_str(&body).unwrap_or(Value::String(body));
+            let items = match page_items(&value) {
+                Some(items) => items,
+                None => {
+                    println!("{}", value);
+                    return Ok(());
+                }
+            };
+            for item in items {
+                println!("{}", item);
+            }
+            if method.to_uppercase() != "GET" {
+                return Ok(());
+            }
+            match next_page(&value, &url, items.len()) {
+                Some(next) => url = next,
+                None => return Ok(()),
+            }
+        }
+    }
+
+    /// Sign and send a request, retrying according to the policy.
+    fn send(
+        &self,
+        method: &str,
+        url: &str,
+        body: Option<&str>,
+    ) -> Result<Reply, Failure> {
+        let method = Method::from_bytes(method.to_uppercase().as_bytes())
+            .map_err(|_| {
+                Failure::new("arguments", format!("invalid method: {}", method))
+            })?;
+
+        let mut attempt = 1;
+        loop {
+            // Create the request
+            let mut request = self.client.request(method.clone(), url);
+
+            // Add the optional JSON body
+            if let Some(body) = body {
+                request = request
+                    .header("Content-Type", "application/json")
+                    .body(body.to_string());
+            }
+
+            let mut request = request
+                .build()
+                .map_err(|err| Failure::new("arguments", err))?;
+
+            // Add the auth header. Each attempt gets a new token.
+            let header = atlassian_app_auth::create_auth_header(
+                &atlassian_app_auth::Parameters::new(
+                    request.method().as_str(),
+                    request.url().clone(),
+                    &self.creds,
+                ),
+            )
+            .map_err(|err| Failure::new("sign", err))?;
+            request.headers_mut().insert(
+                header.name,
+                header.value.parse().expect("failed to parse auth value"),
             );
+
+            let request_url = request.url().clone();
+            if let Fixtures::Replay(dir) = self.fixtures {
+                return Fixture::load(dir, method.as_str(), &request_url, body);
+            }
+
+            let resp = match self.client.execute(request) {
+                Ok(resp) => resp,
+                Err(err) => {
+                    let delay = if err.is_connect() || err.is_timeout() {
+                        self.policy.connection_retry_delay(
+                            attempt,
+                            method.as_str(),
+                            !err.is_connect(),
+                        )
+                    } else {
+                        None
+                    };
+                    match delay {
+                        Some(delay) => {
+                            eprintln!("{}, retrying in {:?}", err, delay);
+                            thread::sleep(delay);
+                            attempt += 1;
+                            continue;
+                        }
+                        None => return Err(Failure::new("network", err)),
+                    }
+                }
+            };
+            let retry_after = resp
+                .headers()
+                .get("Retry-After")
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse().ok())
+                .map(Duration::from_secs);
+            if let Some(delay) = self.policy.retry_delay(
+                attempt,
+                method.as_str(),
+                resp.status().as_u16(),
+                retry_after,
+            ) {
+                eprintln!("got {}, retrying in {:?}", resp.status(), delay);
+                thread::sleep(delay);
+                attempt += 1;
+                continue;
+            }
+
+            let reply = Reply {
+                status: resp.status().as_u16(),
+                body: resp
+                    .text()
+                    .map_err(|err| Failure::new("network", err))?,
+            };
+            if let Fixtures::Record(dir) = self.fixtures {
+                Fixture::save(
+                    dir,
+                    method.as_str(),
+                    &request_url,
+                    body,
+                    &reply,
+                )?;
+            }
+            return Ok(reply);
+        }
+    }
+}
+
+fn print_output(output: String) {
+    if !output.is_empty() {
+        println!("{}", output);
+    }
+}
+
+/// Get the body of a response, or an error if the status isn't
+/// successful.
+fn check_status(reply: Reply) -> Result<String, Failure> {
+    let status =
+        StatusCode::from_u16(reply.status).expect("invalid status code");
+    if !status.is_success() {
+        // Include the body of the request in the error
+        let body = serde_json::from_str(&reply.body)
+            .unwrap_or(Value::String(reply.body));
+        return Err(Failure {
+            stage: "http",
+            message: status.to_string(),
+            status: Some(reply.status),
+            body: Some(body),
+        });
+    }
+    Ok(reply.body)
+}
+
+/// Get the body of a successful response, pretty-printed if it's JSON.
+fn format_response(reply: Reply) -> Result<String, Failure> {
+    let body = check_status(reply)?;
+    Ok(match serde_json::from_str::<Value>(&body) {
+        Ok(value) => serde_json::to_string_pretty(&value)
+            .expect("failed to format response"),
+        Err(_) => body,
+    })
+}
+
+/// Fields that hold the items of a page of results.
+const PAGE_FIELDS: &[&str] = &["issues", "values", "results", "comments"];
+
+/// Get the items of a response that is an array or a page of results.
+fn page_items(value: &Value) -> Option<&Vec<Value>> {
+    match value {
+        Value::Array(items) => Some(items),
+        _ => PAGE_FIELDS
+            .iter()
+            .find_map(|field| value.get(field)?.as_array()),
+    }
+}
+
+/// Get the URL of the page after `page`, which was fetched from `url`
+/// and has `count` items.
+fn next_page(page: &Value, url: &Url, count: usize) -> Option<Url> {
+    // Confluence links to the next page. The link is relative to the
+    // site's context path (/wiki) in the v1 API.
+    if let Some(next) = page.pointer("/_links/next").and_then(Value::as_str) {
+        if url.path().starts_with("/wiki/") && !next.starts_with("/wiki/") {
+            return url.join(&format!("/wiki{}", next)).ok();
+        }
+        return url.join(next).ok();
+    }
+
+    // Jira's newer search API has a token for the next page.
+    if let Some(token) = page.get("nextPageToken").and_then(Value::as_str) {
+        return Some(with_query_param(url, "nextPageToken", token));
+    }
+
+    // Other Jira APIs have an offset, and either the total number of
+    // items or a flag on the last page.
+    let start_at = page.get("startAt")?.as_u64()?;
+    let next = start_at + count as u64;
+    let total = page.get("total").and_then(Value::as_u64);
+    let is_last = page.get("isLast").and_then(Value::as_bool) == Some(true);
+    if count == 0 || is_last || total.is_some_and(|total| next >= total) {
+        return None;
+    }
+    Some(with_query_param(url, "startAt", &next.to_string()))
+}
+
+/// Copy `url`, replacing the query parameter `name` with `value`.
+fn with_query_param(url: &Url, name: &str, value: &str) -> Url {
+    let pairs: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(key, _)| key != name)
+        .map(|(key, value)| (key.into_owned(), value.into_owned()))
+        .collect();
+    let mut url = url.clone();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(pairs)
+        .append_pair(name, value);
+    url
+}
+
+fn read_body(settings: &Settings, path: Option<PathBuf>) -> Option<String> {
+    path.map(|path| {
+        fs::read_to_string(&path).unwrap_or_else(|err| {
+            settings.fail(Failure::new(
+                "arguments",
+                format!("failed to read {}: {}", path.display(), err),
+            ))
+        })
+    })
+}
+
+/// Parse an interval such as "30", "30s", "5m", or "1h".
+fn parse_interval(value: &str) -> Result<Duration, String> {
+    let (number, unit) = match value.find(|c: char| !c.is_ascii_digit()) {
+        Some(i) => value.split_at(i),
+        None => (value, "s"),
+    };
+    let number: u64 = number
+        .parse()
+        .map_err(|_| format!("invalid interval: {}", value))?;
+    let secs = match unit {
+        "s" => number,
+        "m" => number * 60,
+        "h" => number * 60 * 60,
+        _ => return Err(format!("invalid interval unit: {}", unit)),
+    };
+    if secs == 0 {
+        return Err("interval must be greater than zero".into());
+    }
+    Ok(Duration::from_secs(secs))
+}
+
+fn send(opt: SendOpt, settings: &Settings) {
+    let session = Session::new(opt.creds, settings);
+    let body = read_body(settings, opt.body);
+
+    let interval = match opt.watch {
+        Some(interval) => interval,
+        None => {
+            if let Err(failure) =
+                session.print(&opt.method, &opt.url, body.as_deref())
+            {
+                settings.fail(failure);
+            }
+            return;
+        }
+    };
+
+    // Repeat the request until interrupted. Session::send signs each
+    // request, so every call gets a fresh token.
+    let mut previous = None;
+    for count in 1.. {
+        let result = session
+            .send(&opt.method, &opt.url, body.as_deref())
+            .and_then(format_response);
+        let output = match &result {
+            Ok(output) => output.clone(),
+            Err(failure) => failure.to_string(),
+        };
+        if !opt.changes_only || previous.as_ref() != Some(&output) {
+            println!("--- response {}", count);
+            match &result {
+                Ok(output) => println!("{}", output),
+                Err(failure) => settings.report(failure),
+            }
+            previous = Some(output);
+        }
+        thread::sleep(interval);
+    }
+}
+
+fn parse_base_url(settings: &Settings, base_url: &str) -> Url {
+    Url::parse(base_url).unwrap_or_else(|err| {
+        settings.fail(Failure::new(
+            "arguments",
+            format!("invalid base url: {}", err),
+        ))
+    })
+}
+
+fn repl(opt: ReplOpt, settings: &Settings) {
+    let session = Session::new(opt.creds, settings);
+    let base_url = parse_base_url(settings, &opt.base_url);
+
+    let mut history: Vec<String> = match &opt.history {
+        Some(path) => fs::read_to_string(path)
+            .unwrap_or_default()
+            .lines()
+            .map(String::from)
+            .collect(),
+        None => Vec::new(),
+    };
+    let mut history_file = opt.history.as_ref().map(|path| {
+        OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .unwrap_or_else(|err| {
+                settings.fail(Failure::new(
+                    "history",
+                    format!("failed to open {}: {}", path.display(), err),
+                ))
+            })
+    });
+
+    println!("{}", REPL_HELP);
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    loop {
+        print!("{}> ", base_url.host_str().unwrap_or_default());
+        io::stdout().flush().expect("failed to flush stdout");
+        let mut line = match lines.next() {
+            Some(Ok(line)) => line,
+            Some(Err(err)) => settings.fail(Failure::new(
+                "arguments",
+                format!("failed to read stdin: {}", err),
+            )),
+            None => break,
+        };
+
+        // Re-run a command from the history
+        if let Some(n) = line.trim().strip_prefix('!') {
+            match n.parse::<usize>().ok().and_then(|n| history.get(n)) {
+                Some(previous) => {
+                    println!("{}", previous);
+                    line = previous.clone();
+                }
+                None => {
+                    println!("no such history entry: {}", n);
+                    continue;
+                }
+            }
+        }
+
+        let line = line.trim();
+        let (command, rest) =
+            line.split_once(char::is_whitespace).unwrap_or((line, ""));
+        match command {
+            "" => continue,
+            "quit" | "exit" => break,
+            "help" => {
+                println!("{}", REPL_HELP);
+                continue;
+            }
+            "history" => {
+                for (i, entry) in history.iter().enumerate() {
+                    println!("{:4}  {}", i, entry);
+                }
+                continue;
+            }
+            _ => {}
+        }
+
+        history.push(line.to_string());
+        if let Some(file) = &mut history_file {
+            if let Err(err) = writeln!(file, "{}", line) {
+                settings.fail(Failure::new(
+                    "history",
+                    format!("failed to write history: {}", err),
+                ));
+            }
+        }
+
+        let rest = rest.trim();
+        let (path, body) = match rest.split_once(char::is_whitespace) {
+            Some((path, body)) => (path, Some(body.trim())),
+            None => (rest, None),
+        };
+        if path.is_empty() {
+            println!("usage: <method> <path> [json]");
+            continue;
+        }
+        let result = base_url
+            .join(path)
+            .map_err(|err| {
+                Failure::new("arguments", format!("invalid path: {}", err))
+            })
+            .and_then(|url| session.print(command, url.as_str(), body));
+        if let Err(failure) = result {
+            settings.report(&failure);
+        }
+    }
+}
+
+/// Parameter of an OpenAPI operation.
+struct Parameter {
+    name: String,
+    location: String,
+    required: bool,
+    description: String,
+}
+
+/// Operation in an OpenAPI spec.
+struct Operation {
+    method: String,
+    path: String,
+    summary: String,
+    parameters: Vec<Parameter>,
+}
+
+impl Operation {
+    /// Get all operations in `spec`, keyed by operation ID.
+    fn load_all(spec: &Value) -> BTreeMap<String, Operation> {
+        let mut operations = BTreeMap::new();
+        let paths = spec["paths"].as_object().into_iter().flatten();
+        for (path, item) in paths {
+            let methods = item.as_object().into_iter().flatten();
+            for (method, op) in methods {
+                let id = match op["operationId"].as_str() {
+                    Some(id) => id,
+                    // Not an operation, e.g. path-level "parameters"
+                    None => continue,
+                };
+                // Path-level parameters apply to every operation on
+                // the path.
+                let parameters = [&item["parameters"], &op["parameters"]]
+                    .iter()
+                    .filter_map(|params| params.as_array())
+                    .flatten()
+                    .map(|param| Parameter {
+                        name: param["name"].as_str().unwrap_or("").into(),
+                        location: param["in"].as_str().unwrap_or("").into(),
+                        required: param["required"].as_bool() == Some(true),
+                        description: param["description"]
+                            .as_str()
+                            .unwrap_or("")
+                            .into(),
+                    })
+                    .collect();
+                operations.insert(
+                    id.to_string(),
+                    Operation {
+                        method: method.to_uppercase(),
+                        path: path.clone(),
+                        summary: op["summary"].as_str().unwrap_or("").into(),
+                        parameters,
+                    },
+                );
+            }
+        }
+        operations
+    }
+
+    /// Build the URL of the operation from `args`, checking that every
+    /// required parameter is present and every argument is a known
+    /// path or query parameter.
+    fn url(&self, base: &Url, args: &[String]) -> Result<Url, String> {
+        let mut path = self.path.clone();
+        let mut query = Vec::new();
+        let mut seen = Vec::new();
+        for arg in args {
+            let (name, value) = arg
+                .split_once('=')
+                .ok_or_else(|| format!("expected name=value: {}", arg))?;
+            let param = self
+                .parameters
+                .iter()
+                .find(|param| param.name == name)
+                .ok_or_else(|| format!("unknown parameter: {}", name))?;
+            match param.location.as_str() {
+                "path" => {
+                    path = path.replace(&format!("{{{}}}", name), value);
+                }
+                "query" => query.push((name, value)),
+                location => {
+                    return Err(format!(
+                        "unsupported {} parameter: {}",
+                        location, name
+                    ))
+                }
+            }
+            seen.push(name);
+        }
+        for param in &self.parameters {
+            if param.required && !seen.contains(&param.name.as_str()) {
+                return Err(format!(
+                    "missing required parameter: {}",
+                    param.name
+                ));
+            }
+        }
+
+        let mut url = base.join(&path).map_err(|err| err.to_string())?;
+        if !query.is_empty() {
+            url.query_pairs_mut().extend_pairs(query);
+        }
+        Ok(url)
+    }
+}
+
+fn load_spec(spec: &str) -> Result<Value, Failure> {
+    let text = if spec.starts_with("https://") || spec.starts_with("http://") {
+        reqwest::blocking::get(spec)
+            .and_then(|resp| resp.error_for_status())
+            .and_then(|resp| resp.text())
+            .map_err(|err| {
+                Failure::new(
+                    "spec",
+                    format!("failed to download spec: {}", err),
+                )
+            })?
+    } else {
+        fs::read_to_string(spec).map_err(|err| {
+            Failure::new("spec", format!("failed to read spec file: {}", err))
+        })?
+    };
+    serde_json::from_str(&text).map_err(|err| {
+        Failure::new("spec", format!("invalid spec JSON: {}", err))
+    })
+}
+
+fn openapi(opt: OpenApiOpt, settings: &Settings) {
+    let spec =
+        load_spec(&opt.spec).unwrap_or_else(|failure| settings.fail(failure));
+    let operations = Operation::load_all(&spec);
+
+    let id = match opt.operation {
+        Some(id) => id,
+        None => {
+            for (id, op) in &operations {
+                println!("{:40} {:6} {}", id, op.method, op.summary);
+            }
+            return;
+        }
+    };
+    let op = match operations.get(&id) {
+        Some(op) => op,
+        None => settings.fail(Failure::new(
+            "arguments",
+            format!("unknown operation: {}", id),
+        )),
+    };
+
+    let base_url = parse_base_url(settings, &opt.base_url);
+    let url = match op.url(&base_url, &opt.param) {
+        Ok(url) => url,
+        Err(err) => {
+            settings.report(&Failure::new("arguments", err));
+            if settings.error_format == ErrorFormat::Json {
+                std::process::exit(1);
+            }
+            eprintln!("\n{} {} {}", op.method, op.path, op.summary);
+            for param in &op.parameters {
+                eprintln!(
+                    "  -p {}=<{}>{}  {}",
+                    param.name,
+                    param.location,
+                    if param.required { " (required)" } else { "" },
+                    param.description.lines().next().unwrap_or("")
+                );
+            }
+            std::process::exit(1);
+        }
+    };
+
+    let session = Session::new(opt.creds, settings);
+    let body = read_body(settings, opt.body);
+    if let Err(failure) =
+        session.print(&op.method, url.as_str(), body.as_deref())
+    {
+        settings.fail(failure);
+    }
+}
+
+/// Print the result of a `creds doctor` step. Failures exit the
+/// process.
+fn check<T>(
+    settings: &Settings,
+    step: &'static str,
+    result: Result<T, String>,
+) -> T {
+    match result {
+        Ok(value) => {
+            println!("ok    {}", step);
+            value
+        }
+        Err(err) if settings.error_format == ErrorFormat::Json => {
+            settings.fail(Failure::new(step, err))
         }
         Err(err) => {
-            // Print an error including the body of the request
-            println!(
-                "request failed: {}, body: {}",
-                err,
-                resp.text().expect("failed to get body")
+            println!("FAIL  {}: {}", step, err);
+            std::process::exit(1);
+        }
+    }
+}
+
+fn doctor(opt: DoctorOpt, settings: &Settings) {
+    let text = check(
+        settings,
+        "read credentials file",
+        fs::read_to_string(&opt.creds).map_err(|err| err.to_string()),
+    );
+
+    // Check the shape separately from parsing, since the parse error
+    // doesn't say which field is wrong.
+    check(
+        settings,
+        "credentials file shape",
+        serde_json::from_str::<Value>(&text)
+            .map_err(|err| format!("invalid JSON: {}", err))
+            .and_then(|value| {
+                let object =
+                    value.as_object().ok_or("expected a JSON object")?;
+                for names in [["key", "app_key"], ["secret", "shared_secret"]] {
+                    match names.iter().find_map(|name| object.get(*name)) {
+                        Some(Value::String(s)) if !s.is_empty() => {}
+                        Some(_) => {
+                            return Err(format!(
+                                "\"{}\" must be a non-empty string",
+                                names[0]
+                            ))
+                        }
+                        None => {
+                            return Err(format!(
+                                "missing \"{}\" field",
+                                names[0]
+                            ))
+                        }
+                    }
+                }
+                Ok(())
+            }),
+    );
+    let creds = check(
+        settings,
+        "parse credentials",
+        Credentials::from_json(&text).map_err(|err| err.to_string()),
+    );
+
+    let url = check(
+        settings,
+        "build request url",
+        Url::parse(&opt.base_url)
+            .and_then(|base| base.join(&opt.endpoint))
+            .map_err(|err| err.to_string()),
+    );
+    let header = check(
+        settings,
+        "create token",
+        atlassian_app_auth::create_auth_header(
+            &atlassian_app_auth::Parameters::new("GET", url.clone(), &creds),
+        )
+        .map_err(|err| err.to_string()),
+    );
+
+    let resp = Client::new()
+        .get(url.clone())
+        .header(header.name, header.value)
+        .send();
+    let resp = check(
+        settings,
+        "connect (DNS, TCP, and TLS)",
+        resp.map_err(|err| {
+            // Find the underlying cause, which says whether this was a
+            // TLS or a network error.
+            let mut source: &dyn std::error::Error = &err;
+            while let Some(next) = source.source() {
+                source = next;
+            }
+            format!("{}: {}", url, source)
+        }),
+    );
+
+    let status = resp.status();
+    check(
+        settings,
+        "authenticate",
+        match status.as_u16() {
+            401 => Err(format!(
+                "{}: the key or shared secret is wrong, or the app is \
+                 not installed on this site",
+                status
+            )),
+            _ => Ok(()),
+        },
+    );
+    check(
+        settings,
+        "authorize",
+        match status.as_u16() {
+            403 => Err(format!(
+                "{}: the app is missing a scope needed for {}",
+                status, opt.endpoint
+            )),
+            404 => Err(format!(
+                "{}: {} was not found on this site",
+                status, opt.endpoint
+            )),
+            _ => format_response(Reply {
+                status: status.as_u16(),
+                body: resp.text().unwrap_or_default(),
+            })
+            .map(|_| ())
+            .map_err(|failure| failure.to_string()),
+        },
+    );
+}
+
+/// Parse the command line, using the `send` subcommand if none is
+/// given, so that `request <creds-path> <method> <url>` works as it
+/// did before the other subcommands were added.
+fn parse_opt() -> Opt {
+    let args: Vec<String> = std::env::args().collect();
+    let program = args
+        .first()
+        .map(Path::new)
+        .and_then(|path| path.file_name())
+        .and_then(|name| name.to_str())
+        .unwrap_or("request");
+    let mut args: Vec<&str> = args.iter().skip(1).map(String::as_str).collect();
+
+    // Find the first positional argument. Every top-level option
+    // except --help takes a value.
+    let mut i = 0;
+    while i < args.len() && args[i].starts_with('-') && args[i] != "--" {
+        i += if args[i] == "--help" || args[i] == "-h" {
+            1
+        } else {
+            2
+        };
+    }
+    let is_command = |arg: &str| {
+        arg == "help"
+            || <Command as argh::SubCommands>::COMMANDS
+                .iter()
+                .any(|command| command.name == arg)
+    };
+    if i < args.len() && !is_command(args[i]) {
+        args.insert(i, "send");
+    }
+
+    Opt::from_args(&[program], &args).unwrap_or_else(|exit| match exit.status {
+        Ok(()) => {
+            println!("{}", exit.output);
+            std::process::exit(0);
+        }
+        Err(()) => {
+            eprintln!(
+                "{}\nRun {} --help for more information.",
+                exit.output, program
             );
+            std::process::exit(1);
         }
+    })
+}
+
+fn main() {
+    let opt = parse_opt();
+    let mut settings = Settings {
+        attempts: opt.attempts,
+        fixtures: Fixtures::Network,
+        error_format: opt.error_format,
+        output: opt.output,
+    };
+    settings.fixtures = match (opt.record, opt.replay) {
+        (None, None) => Fixtures::Network,
+        (Some(dir), None) => Fixtures::Record(dir),
+        (None, Some(dir)) => Fixtures::Replay(dir),
+        (Some(_), Some(_)) => settings.fail(Failure::new(
+            "arguments",
+            "--record and --replay can't be used together",
+        )),
+    };
+    match opt.command {
+        Command::Send(send_opt) => send(send_opt, &settings),
+        Command::Repl(repl_opt) => repl(repl_opt, &settings),
+        Command::OpenApi(openapi_opt) => openapi(openapi_opt, &settings),
+        Command::Creds(creds_opt) => match creds_opt.command {
+            CredsCommand::Doctor(doctor_opt) => doctor(doctor_opt, &settings),
+        },
     }
 }
@@ -0,0 +1,76 @@
+#ifndef ATLASSIAN_APP_AUTH_H
+#define ATLASSIAN_APP_AUTH_H
+
+/* Generated by cbindgen. Do not edit by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+// Result of an FFI call.
+typedef enum AtlassianAppAuthStatus {
+  // The call succeeded.
+  ATLASSIAN_APP_AUTH_STATUS_OK = 0,
+  // An argument was null or not valid UTF-8.
+  ATLASSIAN_APP_AUTH_STATUS_INVALID_ARGUMENT = 1,
+  // The URL could not be parsed or signed.
+  ATLASSIAN_APP_AUTH_STATUS_INVALID_URL = 2,
+  // The token's signature, expiration time, or format is invalid.
+  ATLASSIAN_APP_AUTH_STATUS_VERIFICATION_FAILED = 3,
+  // The token's query string hash does not match the request.
+  ATLASSIAN_APP_AUTH_STATUS_QSH_MISMATCH = 4,
+  // Any other error.
+  ATLASSIAN_APP_AUTH_STATUS_ERROR = 5,
+} AtlassianAppAuthStatus;
+
+// Create the value of the Authorization header for a request, e.g.
+// `"JWT eyJ0eXAi..."`, and store it in `*out`.
+//
+// # Safety
+//
+// The string arguments must be null or point to null-terminated
+// strings, and `out` must be null or valid for writes.
+AtlassianAppAuthStatus atlassian_app_auth_create_auth_header(const char *method,
+                                                             const char *url,
+                                                             const char *app_key,
+                                                             const char *shared_secret,
+                                                             uint64_t valid_for_secs,
+                                                             char **out);
+
+// Create the query string hash ("qsh" claim) for a request and store
+// it in `*out`.
+//
+// # Safety
+//
+// The string arguments must be null or point to null-terminated
+// strings, and `out` must be null or valid for writes.
+AtlassianAppAuthStatus atlassian_app_auth_query_string_hash(const char *method,
+                                                            const char *url,
+                                                            char **out);
+
+// Verify a JWT sent by Atlassian. The token should not include the
+// `"JWT "` prefix.
+//
+// # Safety
+//
+// The arguments must be null or point to null-terminated strings.
+AtlassianAppAuthStatus atlassian_app_auth_verify_token(const char *token,
+                                                       const char *method,
+                                                       const char *url,
+                                                       const char *shared_secret);
+
+// Get a description of the last error on this thread, or null if
+// there hasn't been one. The string is owned by the library and is
+// valid until the next call on this thread; it must not be freed.
+const char *atlassian_app_auth_last_error(void);
+
+// Free a string returned by this library. Passing null does nothing.
+//
+// # Safety
+//
+// `s` must be null or a string returned by this library that has not
+// already been freed.
+void atlassian_app_auth_free_string(char *s);
+
+#endif  /* ATLASSIAN_APP_AUTH_H */
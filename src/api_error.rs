@@ -0,0 +1,169 @@
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+
+/// Error body in any of the formats described in [`ApiError`].
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Body {
+    #[serde(default)]
+    error_messages: Vec<String>,
+    #[serde(default)]
+    errors: Option<Errors>,
+    #[serde(default)]
+    message: Option<String>,
+    #[serde(default)]
+    error: Option<String>,
+    #[serde(default, rename = "error_description")]
+    error_description: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum Errors {
+    /// Jira's errors, keyed by field name.
+    Fields(BTreeMap<String, String>),
+    /// Confluence v2's list of error objects.
+    List(Vec<ErrorObject>),
+}
+
+#[derive(Deserialize)]
+struct ErrorObject {
+    #[serde(default)]
+    code: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    detail: Option<String>,
+}
+
+/// Error response from an Atlassian API.
+///
+/// [`ApiError::parse`] understands these response bodies:
+/// * Jira: `{"errorMessages": [...], "errors": {"field": "message"}}`
+/// * Confluence: `{"statusCode": 404, "message": "..."}`
+/// * Confluence v2: `{"errors": [{"code": "...", "title": "...",
+///   "detail": "..."}]}`
+/// * OAuth 2.0: `{"error": "invalid_grant", "error_description":
+///   "..."}`
+///
+/// Other bodies, such as HTML error pages, are ignored, so only the
+/// status is set.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ApiError {
+    /// HTTP status of the response.
+    pub status: u16,
+
+    /// Errors that aren't about a particular field of the request.
+    pub messages: Vec<String>,
+
+    /// Errors about particular fields of the request, keyed by field
+    /// name.
+    pub field_errors: BTreeMap<String, String>,
+
+    /// Machine-readable error code, e.g. `invalid_grant` for OAuth
+    /// 2.0 or `NOT_FOUND` for Confluence v2.
+    pub code: Option<String>,
+}
+
+impl ApiError {
+    /// Parse the `body` of a response with an error `status`.
+    pub fn parse(status: u16, body: &str) -> ApiError {
+        let mut api_error = ApiError {
+            status,
+            ..ApiError::default()
+        };
+        let body: Body = match serde_json::from_str(body) {
+            Ok(body) => body,
+            Err(_) => return api_error,
+        };
+
+        api_error.messages = body.error_messages;
+        api_error.messages.extend(body.message);
+        api_error.messages.extend(body.error_description);
+        api_error.code = body.error;
+        match body.errors {
+            Some(Errors::Fields(fields)) => api_error.field_errors = fields,
+            Some(Errors::List(list)) => {
+                for object in list {
+                    if api_error.code.is_none() {
+                        api_error.code = object.code;
+                    }
+                    api_error.messages.extend(object.detail.or(object.title));
+                }
+            }
+            None => {}
+        }
+        api_error
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "status {}", self.status)?;
+        if let Some(code) = &self.code {
+            write!(f, " ({})", code)?;
+        }
+        let fields = self
+            .field_errors
+            .iter()
+            .map(|(field, message)| format!("{}: {}", field, message));
+        let messages: Vec<_> =
+            self.messages.iter().cloned().chain(fields).collect();
+        if !messages.is_empty() {
+            write!(f, ": {}", messages.join("; "))?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ApiError {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse() {
+        let err = ApiError::parse(
+            400,
+            r#"{"errorMessages": ["Bad JQL"], "errors": {"summary": "Required"}}"#,
+        );
+        assert_eq!(err.messages, ["Bad JQL"]);
+        assert_eq!(err.field_errors["summary"], "Required");
+        assert_eq!(err.code, None);
+        assert_eq!(err.to_string(), "status 400: Bad JQL; summary: Required");
+
+        let err = ApiError::parse(
+            404,
+            r#"{"statusCode": 404, "message": "No space with key: X"}"#,
+        );
+        assert_eq!(err.messages, ["No space with key: X"]);
+
+        let err = ApiError::parse(
+            404,
+            r#"{"errors": [{"status": 404, "code": "NOT_FOUND", "title": "Not Found", "detail": null}]}"#,
+        );
+        assert_eq!(err.messages, ["Not Found"]);
+        assert_eq!(err.code.as_deref(), Some("NOT_FOUND"));
+        assert_eq!(err.to_string(), "status 404 (NOT_FOUND): Not Found");
+
+        let err = ApiError::parse(
+            403,
+            r#"{"error": "invalid_grant", "error_description": "Unknown or invalid refresh token."}"#,
+        );
+        assert_eq!(err.code.as_deref(), Some("invalid_grant"));
+        assert_eq!(err.messages, ["Unknown or invalid refresh token."]);
+
+        let err = ApiError::parse(502, "<html>Bad Gateway</html>");
+        assert_eq!(
+            err,
+            ApiError {
+                status: 502,
+                ..ApiError::default()
+            }
+        );
+        assert_eq!(err.to_string(), "status 502");
+    }
+}
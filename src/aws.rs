@@ -0,0 +1,343 @@
+use crate::{store_error, AuthError, Credentials, SharedSecret, Signer};
+use ring::hmac;
+use serde::Deserialize;
+use sha2::Digest;
+use std::env;
+use std::time::{SystemTime, UNIX_EPOCH};
+use url::Url;
+
+fn hmac_sha256(key: &[u8], data: &str) -> Vec<u8> {
+    hmac::sign(&hmac::Key::new(hmac::HMAC_SHA256, key), data.as_bytes())
+        .as_ref()
+        .to_vec()
+}
+
+fn hex_sha256(data: &[u8]) -> String {
+    format!("{:x}", sha2::Sha256::digest(data))
+}
+
+/// Format a Unix timestamp in the ISO 8601 basic format used by AWS,
+/// e.g. "20150830T123600Z".
+fn format_amz_date(unix_secs: u64) -> String {
+    let days = (unix_secs / 86400) as i64;
+    let secs = unix_secs % 86400;
+
+    // Convert days since the epoch to a civil date. See
+    // <http://howardhinnant.github.io/date_algorithms.html#civil_from_days>.
+    let z = days + 719468;
+    let era = z.div_euclid(146097);
+    let doe = z - era * 146097;
+    let yoe = (doe - doe / 1460 + doe / 36524 - doe / 146096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = doy - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = yoe + era * 400 + i64::from(month <= 2);
+
+    format!(
+        "{:04}{:02}{:02}T{:02}{:02}{:02}Z",
+        year,
+        month,
+        day,
+        secs / 3600,
+        secs / 60 % 60,
+        secs % 60
+    )
+}
+
+/// Inputs for an AWS Signature Version 4 signature.
+struct SigV4<'a> {
+    method: &'a str,
+    path: &'a str,
+    /// Headers to sign, including "host" and "x-amz-date". Names must
+    /// be lowercase.
+    headers: &'a [(&'a str, &'a str)],
+    payload: &'a [u8],
+    amz_date: &'a str,
+    region: &'a str,
+    service: &'a str,
+    access_key_id: &'a str,
+    secret_access_key: &'a SharedSecret,
+}
+
+impl SigV4<'_> {
+    /// Create the value of the Authorization header. See
+    /// <https://docs.aws.amazon.com/general/latest/gr/sigv4_signing.html>.
+    fn authorization(&self) -> String {
+        let mut headers = self.headers.to_vec();
+        headers.sort_unstable();
+        let canonical_headers = headers
+            .iter()
+            .map(|(name, value)| format!("{}:{}\n", name, value.trim()))
+            .collect::<String>();
+        let signed_headers = headers
+            .iter()
+            .map(|(name, _)| *name)
+            .collect::<Vec<_>>()
+            .join(";");
+
+        let canonical_request = format!(
+            "{}\n{}\n\n{}\n{}\n{}",
+            self.method,
+            self.path,
+            canonical_headers,
+            signed_headers,
+            hex_sha256(self.payload)
+        );
+
+        let date = &self.amz_date[..8];
+        let scope =
+            format!("{}/{}/{}/aws4_request", date, self.region, self.service);
+        let string_to_sign = format!(
+            "AWS4-HMAC-SHA256\n{}\n{}\n{}",
+            self.amz_date,
+            scope,
+            hex_sha256(canonical_request.as_bytes())
+        );
+
+        let key = format!("AWS4{}", self.secret_access_key.expose());
+        let key = hmac_sha256(key.as_bytes(), date);
+        let key = hmac_sha256(&key, self.region);
+        let key = hmac_sha256(&key, self.service);
+        let key = hmac_sha256(&key, "aws4_request");
+        let signature = hmac_sha256(&key, &string_to_sign)
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<String>();
+
+        format!(
+            "AWS4-HMAC-SHA256 Credential={}/{}, SignedHeaders={}, Signature={}",
+            self.access_key_id, scope, signed_headers, signature
+        )
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct GetSecretValueResponse {
+    secret_string: Option<String>,
+}
+
+/// Client for loading credentials from AWS Secrets Manager.
+///
+/// The secret must contain JSON [`Credentials`].
+///
+/// [`Credentials`]: crate::Credentials
+///
+/// This uses a blocking HTTP client, so it should not be called
+/// directly from async code.
+#[derive(Clone, Debug)]
+pub struct AwsSecretsManager {
+    http: reqwest::blocking::Client,
+    endpoint: Url,
+    region: String,
+    access_key_id: String,
+    secret_access_key: SharedSecret,
+    session_token: Option<SharedSecret>,
+}
+
+impl AwsSecretsManager {
+    /// Create a new `AwsSecretsManager` client for `region`.
+    pub fn new<R: Into<String>, K: Into<String>>(
+        region: R,
+        access_key_id: K,
+        secret_access_key: SharedSecret,
+    ) -> Result<AwsSecretsManager, AuthError> {
+        let region = region.into();
+        let endpoint = Url::parse(&format!(
+            "https://secretsmanager.{}.amazonaws.com/",
+            region
+        ))
+        .map_err(store_error)?;
+        Ok(AwsSecretsManager {
+            http: reqwest::blocking::Client::new(),
+            endpoint,
+            region,
+            access_key_id: access_key_id.into(),
+            secret_access_key,
+            session_token: None,
+        })
+    }
+
+    /// Create a new `AwsSecretsManager` client using the standard
+    /// `AWS_REGION` (or `AWS_DEFAULT_REGION`), `AWS_ACCESS_KEY_ID`,
+    /// `AWS_SECRET_ACCESS_KEY`, and `AWS_SESSION_TOKEN` environment
+    /// variables.
+    pub fn from_env() -> Result<AwsSecretsManager, AuthError> {
+        let var = |name: &str| {
+            env::var(name)
+                .map_err(|_| store_error(format!("{} is not set", name)))
+        };
+        let region =
+            var("AWS_REGION").or_else(|_| var("AWS_DEFAULT_REGION"))?;
+        let client = AwsSecretsManager::new(
+            region,
+            var("AWS_ACCESS_KEY_ID")?,
+            var("AWS_SECRET_ACCESS_KEY")?.into(),
+        )?;
+        Ok(match env::var("AWS_SESSION_TOKEN") {
+            Ok(token) => client.with_session_token(token.into()),
+            Err(_) => client,
+        })
+    }
+
+    /// Set the session token used with temporary credentials.
+    pub fn with_session_token(
+        mut self,
+        session_token: SharedSecret,
+    ) -> AwsSecretsManager {
+        self.session_token = Some(session_token);
+        self
+    }
+
+    /// Override the service endpoint, e.g. to use a VPC endpoint.
+    pub fn with_endpoint(mut self, endpoint: Url) -> AwsSecretsManager {
+        self.endpoint = endpoint;
+        self
+    }
+
+    /// Get the string value of a secret. `secret_id` can be the name
+    /// or ARN of the secret.
+    pub fn get_secret_string(
+        &self,
+        secret_id: &str,
+    ) -> Result<SharedSecret, AuthError> {
+        let payload = serde_json::json!({ "SecretId": secret_id }).to_string();
+        let host = match (self.endpoint.host_str(), self.endpoint.port()) {
+            (Some(host), Some(port)) => format!("{}:{}", host, port),
+            (Some(host), None) => host.to_string(),
+            (None, _) => return Err(store_error("invalid endpoint")),
+        };
+        let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+        let amz_date = format_amz_date(now);
+        let content_type = "application/x-amz-json-1.1";
+        let target = "secretsmanager.GetSecretValue";
+
+        let mut headers = vec![
+            ("content-type", content_type),
+            ("host", host.as_str()),
+            ("x-amz-date", amz_date.as_str()),
+            ("x-amz-target", target),
+        ];
+        if let Some(token) = &self.session_token {
+            headers.push(("x-amz-security-token", token.expose()));
+        }
+        let authorization = SigV4 {
+            method: "POST",
+            path: self.endpoint.path(),
+            headers: &headers,
+            payload: payload.as_bytes(),
+            amz_date: &amz_date,
+            region: &self.region,
+            service: "secretsmanager",
+            access_key_id: &self.access_key_id,
+            secret_access_key: &self.secret_access_key,
+        }
+        .authorization();
+
+        let mut request = self.http.post(self.endpoint.clone());
+        for (name, value) in &headers {
+            if *name != "host" {
+                request = request.header(*name, *value);
+            }
+        }
+        let resp: GetSecretValueResponse = request
+            .header("Authorization", authorization)
+            .body(payload)
+            .send()
+            .and_then(|resp| resp.error_for_status())
+            .and_then(|resp| resp.json())
+            .map_err(store_error)?;
+
+        resp.secret_string
+            .map(SharedSecret::new)
+            .ok_or_else(|| store_error("secret has no string value"))
+    }
+
+    /// Read [`Credentials`] from the JSON secret identified by
+    /// `secret_id`.
+    pub fn credentials(
+        &self,
+        secret_id: &str,
+    ) -> Result<Credentials, AuthError> {
+        Credentials::from_json(self.get_secret_string(secret_id)?.expose())
+    }
+
+    /// Create a [`Signer`] from the JSON secret identified by
+    /// `secret_id`.
+    pub fn signer(&self, secret_id: &str) -> Result<Signer, AuthError> {
+        Ok(self.credentials(secret_id)?.signer())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_server::serve;
+
+    #[test]
+    fn test_format_amz_date() {
+        assert_eq!(format_amz_date(0), "19700101T000000Z");
+        assert_eq!(format_amz_date(1440938160), "20150830T123600Z");
+        assert_eq!(format_amz_date(951782400), "20000229T000000Z");
+    }
+
+    /// "get-vanilla" case from the AWS Signature Version 4 test suite.
+    #[test]
+    fn test_sigv4() {
+        let sig = SigV4 {
+            method: "GET",
+            path: "/",
+            headers: &[
+                ("host", "example.amazonaws.com"),
+                ("x-amz-date", "20150830T123600Z"),
+            ],
+            payload: b"",
+            amz_date: "20150830T123600Z",
+            region: "us-east-1",
+            service: "service",
+            access_key_id: "AKIDEXAMPLE",
+            secret_access_key: &SharedSecret::new(
+                "wJalrXUtnFEMI/K7MDENG+bPxRfiCYEXAMPLEKEY",
+            ),
+        };
+        assert_eq!(
+            sig.authorization(),
+            "AWS4-HMAC-SHA256 \
+             Credential=AKIDEXAMPLE/20150830/us-east-1/service/aws4_request, \
+             SignedHeaders=host;x-amz-date, \
+             Signature=5fa00fa31553b73ebf1942676e86291e8372ff2a2260956d9b8aae1d763fbf31"
+        );
+    }
+
+    #[test]
+    fn test_signer() {
+        let server = serve(vec![(
+            200,
+            serde_json::json!({
+                "Name": "atlassian",
+                "SecretString": r#"{"key": "app", "secret": "secret"}"#,
+            })
+            .to_string(),
+        )]);
+        let client = AwsSecretsManager::new(
+            "us-east-1",
+            String::from("AKID"),
+            "secret".into(),
+        )
+        .unwrap()
+        .with_endpoint(server.url.clone());
+
+        let signer = client.signer("atlassian").unwrap();
+        assert_eq!(signer.app_key(), "app");
+
+        let requests = server.requests.lock().unwrap();
+        let head = &requests[0].head;
+        assert!(head.starts_with("POST / HTTP/1.1"));
+        assert!(head.contains("x-amz-target: secretsmanager.GetSecretValue"));
+        assert!(
+            head.contains("authorization: AWS4-HMAC-SHA256 Credential=AKID/")
+        );
+        assert_eq!(requests[0].body, r#"{"SecretId":"atlassian"}"#);
+    }
+}
@@ -0,0 +1,577 @@
+use crate::redirect::Target;
+use crate::retry::{send, send_async};
+use crate::{
+    request_error, AsyncTenantStore, AuthError, ClockDriftCheck, Header,
+    RateLimiter, RetryPolicy, Signer, TenantAuth, TenantStore,
+};
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use reqwest::redirect::Policy;
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::fmt;
+use std::sync::Arc;
+use url::Url;
+
+fn parse_method(method: &str) -> Result<Method, AuthError> {
+    Method::from_bytes(method.as_bytes()).map_err(request_error)
+}
+
+/// Create an HTTP client that doesn't follow redirects, since they
+/// need a new token. See [`Target`].
+fn async_http() -> reqwest::Client {
+    reqwest::Client::builder()
+        .redirect(Policy::none())
+        .build()
+        .expect("failed to create HTTP client")
+}
+
+/// Blocking version of [`async_http`].
+fn blocking_http() -> reqwest::blocking::Client {
+    reqwest::blocking::Client::builder()
+        .redirect(Policy::none())
+        .build()
+        .expect("failed to create HTTP client")
+}
+
+fn to_json<B: Serialize + ?Sized>(body: &B) -> Result<Vec<u8>, AuthError> {
+    serde_json::to_vec(body).map_err(request_error)
+}
+
+/// Settings shared by the clients.
+#[derive(Debug)]
+pub(crate) struct Options {
+    pub(crate) retry_policy: RetryPolicy,
+    pub(crate) rate_limiter: Option<Arc<RateLimiter>>,
+    pub(crate) clock_drift: Option<Arc<ClockDriftCheck>>,
+}
+
+impl Options {
+    fn new() -> Options {
+        Options {
+            retry_policy: RetryPolicy::default(),
+            rate_limiter: None,
+            clock_drift: None,
+        }
+    }
+}
+
+macro_rules! client_options {
+    ($client:ident) => {
+        impl<S> $client<S> {
+            /// Retry failed requests according to `retry_policy` instead
+            /// of [`RetryPolicy::default`].
+            pub fn with_retry_policy(
+                mut self,
+                retry_policy: RetryPolicy,
+            ) -> Self {
+                self.options.retry_policy = retry_policy;
+                self
+            }
+
+            /// Take a token from `rate_limiter` before each request, using
+            /// the client key as the tenant.
+            pub fn with_rate_limiter(
+                mut self,
+                rate_limiter: Arc<RateLimiter>,
+            ) -> Self {
+                self.options.rate_limiter = Some(rate_limiter);
+                self
+            }
+
+            /// Check the `Date` header of every response for clock
+            /// drift with `check`.
+            pub fn with_clock_drift_check(
+                mut self,
+                check: Arc<ClockDriftCheck>,
+            ) -> Self {
+                self.options.clock_drift = Some(check);
+                self
+            }
+
+            /// Get the [`TenantAuth`] used to sign requests.
+            pub fn auth(&self) -> &TenantAuth<S> {
+                &self.auth
+            }
+        }
+
+        impl<S: fmt::Debug> fmt::Debug for $client<S> {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                f.debug_struct(stringify!($client))
+                    .field("auth", &self.auth)
+                    .field("retry_policy", &self.options.retry_policy)
+                    .field("rate_limiter", &self.options.rate_limiter)
+                    .field("clock_drift", &self.options.clock_drift)
+                    .finish_non_exhaustive()
+            }
+        }
+    };
+}
+
+/// Sends signed requests to the Atlassian site of any tenant, looking
+/// up the tenant's shared secret by client key.
+///
+/// Each attempt at a request is signed with a new token, failed
+/// requests are retried according to a [`RetryPolicy`], and requests
+/// can be throttled with a [`RateLimiter`]. Redirects within the same
+/// site are followed, with a new token for the redirect target, since
+/// the original token's query string hash doesn't match it. Responses
+/// with an error status are returned as [`AuthError::ApiError`] once
+/// retries are exhausted, with Atlassian's error messages parsed from
+/// the body.
+///
+/// This is the async version, which must be used within a Tokio
+/// runtime. [`BlockingTenantClient`] has the same API for synchronous
+/// code.
+///
+/// ```no_run
+/// # async fn example() -> Result<(), atlassian_app_auth::AuthError> {
+/// use atlassian_app_auth::{SharedSecret, SyncTenantStore, TenantAuth, TenantClient};
+/// use std::collections::HashMap;
+/// use url::Url;
+///
+/// let mut tenants = HashMap::new();
+/// tenants.insert("client-key".to_string(), SharedSecret::new("secret"));
+/// let client = TenantClient::new(TenantAuth::new(
+///     "my-app",
+///     SyncTenantStore(tenants),
+/// ));
+///
+/// let url = Url::parse("https://example.atlassian.net/rest/api/3/myself").unwrap();
+/// let myself: serde_json::Value = client.get_json("client-key", &url).await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct TenantClient<S> {
+    auth: TenantAuth<S>,
+    http: reqwest::Client,
+    options: Options,
+}
+
+client_options!(TenantClient);
+
+impl<S: AsyncTenantStore> TenantClient<S> {
+    /// Create a new `TenantClient` that signs requests with `auth`.
+    pub fn new(auth: TenantAuth<S>) -> TenantClient<S> {
+        TenantClient {
+            auth,
+            http: async_http(),
+            options: Options::new(),
+        }
+    }
+
+    /// Send a request without a body to the tenant identified by
+    /// `client_key`.
+    pub async fn send(
+        &self,
+        client_key: &str,
+        method: &str,
+        url: &Url,
+    ) -> Result<reqwest::Response, AuthError> {
+        self.execute(client_key, method, url, None).await
+    }
+
+    /// Send a request with a JSON body to the tenant identified by
+    /// `client_key`.
+    pub async fn send_json<B: Serialize + ?Sized>(
+        &self,
+        client_key: &str,
+        method: &str,
+        url: &Url,
+        body: &B,
+    ) -> Result<reqwest::Response, AuthError> {
+        let body = to_json(body)?;
+        self.execute(client_key, method, url, Some(body)).await
+    }
+
+    /// Send a GET request to the tenant identified by `client_key` and
+    /// parse the JSON response.
+    pub async fn get_json<T: DeserializeOwned>(
+        &self,
+        client_key: &str,
+        url: &Url,
+    ) -> Result<T, AuthError> {
+        self.send(client_key, "GET", url)
+            .await?
+            .json()
+            .await
+            .map_err(request_error)
+    }
+
+    async fn execute(
+        &self,
+        client_key: &str,
+        method: &str,
+        url: &Url,
+        body: Option<Vec<u8>>,
+    ) -> Result<reqwest::Response, AuthError> {
+        let mut target = Target::new(parse_method(method)?, url.clone(), body);
+        let drift = self.options.clock_drift.as_deref();
+        loop {
+            let target_ref = &target;
+            let resp = send_async(
+                &self.options.retry_policy,
+                drift,
+                &target_ref.method,
+                || async {
+                    if let Some(rate_limiter) = &self.options.rate_limiter {
+                        while let Err(wait) =
+                            rate_limiter.try_acquire(client_key)
+                        {
+                            tokio::time::sleep(wait).await;
+                        }
+                    }
+                    let header = self
+                        .auth
+                        .create_auth_header_async(
+                            client_key,
+                            target_ref.method.as_str(),
+                            &target_ref.url,
+                        )
+                        .await?;
+                    Ok(build_request(
+                        self.http.request(
+                            target_ref.method.clone(),
+                            target_ref.url.clone(),
+                        ),
+                        header,
+                        target_ref,
+                    ))
+                },
+            )
+            .await?;
+            if !target.follow(resp.status(), resp.headers())? {
+                return Ok(resp);
+            }
+        }
+    }
+}
+
+/// Blocking version of [`TenantClient`], with the same API.
+///
+/// This uses a blocking HTTP client, so it should not be called
+/// directly from async code.
+pub struct BlockingTenantClient<S> {
+    auth: TenantAuth<S>,
+    http: reqwest::blocking::Client,
+    options: Options,
+}
+
+client_options!(BlockingTenantClient);
+
+impl<S: TenantStore> BlockingTenantClient<S> {
+    /// Create a new `BlockingTenantClient` that signs requests with
+    /// `auth`.
+    pub fn new(auth: TenantAuth<S>) -> BlockingTenantClient<S> {
+        BlockingTenantClient {
+            auth,
+            http: blocking_http(),
+            options: Options::new(),
+        }
+    }
+
+    /// Send a request without a body to the tenant identified by
+    /// `client_key`.
+    pub fn send(
+        &self,
+        client_key: &str,
+        method: &str,
+        url: &Url,
+    ) -> Result<reqwest::blocking::Response, AuthError> {
+        self.execute(client_key, method, url, None)
+    }
+
+    /// Send a request with a JSON body to the tenant identified by
+    /// `client_key`.
+    pub fn send_json<B: Serialize + ?Sized>(
+        &self,
+        client_key: &str,
+        method: &str,
+        url: &Url,
+        body: &B,
+    ) -> Result<reqwest::blocking::Response, AuthError> {
+        let body = to_json(body)?;
+        self.execute(client_key, method, url, Some(body))
+    }
+
+    /// Send a GET request to the tenant identified by `client_key` and
+    /// parse the JSON response.
+    pub fn get_json<T: DeserializeOwned>(
+        &self,
+        client_key: &str,
+        url: &Url,
+    ) -> Result<T, AuthError> {
+        self.send(client_key, "GET", url)?
+            .json()
+            .map_err(request_error)
+    }
+
+    fn execute(
+        &self,
+        client_key: &str,
+        method: &str,
+        url: &Url,
+        body: Option<Vec<u8>>,
+    ) -> Result<reqwest::blocking::Response, AuthError> {
+        let target = Target::new(parse_method(method)?, url.clone(), body);
+        execute_blocking(
+            &self.http,
+            &self.options,
+            client_key,
+            target,
+            |target| {
+                self.auth.create_auth_header(
+                    client_key,
+                    target.method.as_str(),
+                    &target.url,
+                )
+            },
+        )
+    }
+}
+
+/// Add the auth header, and the JSON body if any, to an async request.
+/// [`execute_blocking`] does the same for blocking requests.
+fn build_request(
+    request: reqwest::RequestBuilder,
+    header: Header,
+    target: &Target,
+) -> reqwest::RequestBuilder {
+    let request = request
+        .header(header.name, header.value)
+        .header(ACCEPT, "application/json");
+    match &target.body {
+        Some(body) => request
+            .header(CONTENT_TYPE, "application/json")
+            .body(body.clone()),
+        None => request,
+    }
+}
+
+/// Send a request with a blocking client, following redirects and
+/// retrying according to `options`. `sign` creates the auth header for
+/// each attempt, and `tenant` is used for rate limiting.
+fn execute_blocking(
+    http: &reqwest::blocking::Client,
+    options: &Options,
+    tenant: &str,
+    mut target: Target,
+    sign: impl Fn(&Target) -> Result<Header, AuthError>,
+) -> Result<reqwest::blocking::Response, AuthError> {
+    loop {
+        let resp = send(
+            &options.retry_policy,
+            options.clock_drift.as_deref(),
+            &target.method,
+            || {
+                if let Some(rate_limiter) = &options.rate_limiter {
+                    rate_limiter.acquire(tenant);
+                }
+                let header = sign(&target)?;
+                let request = http
+                    .request(target.method.clone(), target.url.clone())
+                    .header(header.name, header.value)
+                    .header(ACCEPT, "application/json");
+                Ok(match &target.body {
+                    Some(body) => request
+                        .header(CONTENT_TYPE, "application/json")
+                        .body(body.clone()),
+                    None => request,
+                })
+            },
+        )?;
+        if !target.follow(resp.status(), resp.headers())? {
+            return Ok(resp);
+        }
+    }
+}
+
+/// Blocking client for one site that signs requests with an app's
+/// [`Signer`]. This is the common part of the API helpers such as
+/// [`Webhooks`].
+///
+/// [`Webhooks`]: crate::Webhooks
+#[derive(Debug)]
+pub(crate) struct SiteClient {
+    http: reqwest::blocking::Client,
+    base: Url,
+    signer: Signer,
+    pub(crate) options: Options,
+}
+
+impl SiteClient {
+    pub(crate) fn new(base: &Url, signer: Signer) -> SiteClient {
+        SiteClient {
+            http: blocking_http(),
+            base: base.clone(),
+            signer,
+            options: Options::new(),
+        }
+    }
+
+    /// Base URL of the site.
+    pub(crate) fn base(&self) -> &Url {
+        &self.base
+    }
+
+    /// Signer used for requests.
+    pub(crate) fn signer(&self) -> &Signer {
+        &self.signer
+    }
+
+    /// Send a signed request. The site's base URL is used as the
+    /// tenant for rate limiting.
+    pub(crate) fn execute<B: Serialize + ?Sized>(
+        &self,
+        method: &str,
+        url: Url,
+        body: Option<&B>,
+    ) -> Result<reqwest::blocking::Response, AuthError> {
+        let body = body.map(to_json).transpose()?;
+        let target = Target::new(parse_method(method)?, url, body);
+        execute_blocking(
+            &self.http,
+            &self.options,
+            self.base.as_str(),
+            target,
+            |target| {
+                self.signer
+                    .create_auth_header(target.method.as_str(), &target.url)
+            },
+        )
+    }
+
+    /// Send a signed request and parse the JSON response.
+    pub(crate) fn send<B: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        method: &str,
+        url: Url,
+        body: Option<&B>,
+    ) -> Result<T, AuthError> {
+        self.execute(method, url, body)?
+            .json()
+            .map_err(request_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_server::{serve, serve_with_headers, FakeServer};
+    use crate::{SharedSecret, SyncTenantStore};
+    use std::collections::HashMap;
+
+    fn tenants() -> HashMap<String, SharedSecret> {
+        let mut tenants = HashMap::new();
+        tenants.insert("tenant".to_string(), SharedSecret::new("secret"));
+        tenants
+    }
+
+    /// Check that the request with `head` was signed by "app" for
+    /// `method` and `url`. Without the verify feature, only the claims
+    /// are checked, not the signature.
+    fn check_token(head: &str, method: &str, url: &Url) {
+        let token = head
+            .lines()
+            .find_map(|line| line.strip_prefix("authorization: JWT "))
+            .unwrap();
+
+        #[cfg(feature = "verify")]
+        let claims = crate::Verifier::new(SharedSecret::new("secret"))
+            .verify_claims(token, method, url)
+            .unwrap();
+        #[cfg(not(feature = "verify"))]
+        let claims =
+            jsonwebtoken::dangerous_insecure_decode::<crate::Claims>(token)
+                .unwrap()
+                .claims;
+
+        assert_eq!(claims.iss, "app");
+        assert_eq!(claims.qsh, crate::create_query_string_hash(method, url));
+    }
+
+    fn check_requests(server: &FakeServer) {
+        let requests = server.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        for (request, method) in requests.iter().zip(["POST", "GET"]) {
+            let url = server.url.join("/rest/api/3/issue?a=1").unwrap();
+            check_token(&request.head, method, &url);
+        }
+        assert!(requests[0].head.contains("content-type: application/json"));
+        assert_eq!(requests[0].body, r#"{"fields":{}}"#);
+    }
+
+    fn responses() -> Vec<(u16, String)> {
+        vec![(201, "{}".into()), (200, r#"{"key": "TEST-1"}"#.into())]
+    }
+
+    #[test]
+    fn test_blocking_tenant_client() {
+        let server = serve(responses());
+        let client =
+            BlockingTenantClient::new(TenantAuth::new("app", tenants()));
+        let url = server.url.join("/rest/api/3/issue?a=1").unwrap();
+
+        let body = serde_json::json!({"fields": {}});
+        let resp = client.send_json("tenant", "POST", &url, &body).unwrap();
+        assert_eq!(resp.status(), 201);
+        let issue: serde_json::Value = client.get_json("tenant", &url).unwrap();
+        assert_eq!(issue["key"], "TEST-1");
+        check_requests(&server);
+
+        assert!(matches!(
+            client.send("other", "GET", &url),
+            Err(AuthError::UnknownTenant)
+        ));
+    }
+
+    #[test]
+    fn test_tenant_client() {
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let server = serve(responses());
+        let client = TenantClient::new(TenantAuth::new(
+            "app",
+            SyncTenantStore(tenants()),
+        ));
+        let url = server.url.join("/rest/api/3/issue?a=1").unwrap();
+
+        runtime.block_on(async {
+            let body = serde_json::json!({"fields": {}});
+            let resp = client
+                .send_json("tenant", "POST", &url, &body)
+                .await
+                .unwrap();
+            assert_eq!(resp.status(), 201);
+            let issue: serde_json::Value =
+                client.get_json("tenant", &url).await.unwrap();
+            assert_eq!(issue["key"], "TEST-1");
+        });
+        check_requests(&server);
+    }
+
+    #[test]
+    fn test_redirect() {
+        let server = serve_with_headers(vec![
+            (302, "Location: /rest/api/3/moved?b=2\r\n", String::new()),
+            (200, "", "{}".into()),
+        ]);
+        let client =
+            BlockingTenantClient::new(TenantAuth::new("app", tenants()));
+        let url = server.url.join("/rest/api/3/issue?a=1").unwrap();
+        let body = serde_json::json!({"fields": {}});
+        client.send_json("tenant", "POST", &url, &body).unwrap();
+
+        // The redirect is followed with a GET, and a new token for the
+        // new path and query.
+        let requests = server.requests.lock().unwrap();
+        assert!(requests[1]
+            .head
+            .starts_with("GET /rest/api/3/moved?b=2 HTTP/1.1"));
+        assert!(requests[1].body.is_empty());
+        let moved = server.url.join("/rest/api/3/moved?b=2").unwrap();
+        check_token(&requests[1].head, "GET", &moved);
+    }
+}
@@ -0,0 +1,65 @@
+use crate::AuthError;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+/// Source of the current time, used to set and check token issue and
+/// expiration times.
+///
+/// [`SystemClock`] is used by default. On targets where
+/// [`SystemTime::now`] is not available, such as
+/// `wasm32-unknown-unknown`, provide a different implementation with
+/// [`Signer::with_clock`] or `Verifier::with_clock`. Closures that
+/// return a [`SystemTime`] implement this trait, so in a browser or
+/// Cloudflare Worker this could be:
+///
+/// ```ignore
+/// signer.with_clock(Arc::new(|| {
+///     UNIX_EPOCH + Duration::from_millis(js_sys::Date::now() as u64)
+/// }))
+/// ```
+///
+/// [`Signer::with_clock`]: crate::Signer::with_clock
+pub trait Clock: Send + Sync {
+    /// Get the current time.
+    fn now(&self) -> SystemTime;
+}
+
+/// [`Clock`] that uses [`SystemTime::now`].
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> SystemTime {
+        SystemTime::now()
+    }
+}
+
+impl<F: Fn() -> SystemTime + Send + Sync> Clock for F {
+    fn now(&self) -> SystemTime {
+        self()
+    }
+}
+
+/// Get the current time from `clock` as seconds since the Unix epoch.
+pub(crate) fn unix_time(clock: &dyn Clock) -> Result<u64, AuthError> {
+    Ok(clock.now().duration_since(UNIX_EPOCH)?.as_secs())
+}
+
+/// Monotonic timer for [`AuthEvent::elapsed`]. [`Instant::now`] panics
+/// on `wasm32-unknown-unknown`, so nothing is measured there.
+///
+/// [`AuthEvent::elapsed`]: crate::AuthEvent::elapsed
+pub(crate) struct Stopwatch(Option<Instant>);
+
+impl Stopwatch {
+    pub(crate) fn start() -> Stopwatch {
+        if cfg!(all(target_arch = "wasm32", target_os = "unknown")) {
+            Stopwatch(None)
+        } else {
+            Stopwatch(Some(Instant::now()))
+        }
+    }
+
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.0.map(|start| start.elapsed()).unwrap_or_default()
+    }
+}
@@ -0,0 +1,337 @@
+use crate::{store_error, AuthError, SharedSecret, Signer};
+use serde::{Deserialize, Deserializer};
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::time::Duration;
+
+/// Deserialize a [`Duration`] from a number of seconds.
+pub(crate) fn deserialize_secs<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Duration, D::Error> {
+    u64::deserialize(deserializer).map(Duration::from_secs)
+}
+
+fn default_valid_for() -> Duration {
+    Signer::DEFAULT_VALID_FOR
+}
+
+/// Connect App credentials.
+///
+/// This can be deserialized from a configuration file. `valid_for` is
+/// a number of seconds and is optional. The `key` and `secret` field
+/// names used by the example's credentials file are also accepted:
+///
+/// ```json
+/// {"key": "com.example.app", "secret": "..."}
+/// ```
+///
+/// With the `figment` feature, credentials can also be loaded from
+/// layered configuration; see `Credentials::figment`. With the
+/// `keychain` feature, they can be read from the operating system's
+/// keychain; see `Credentials::from_keychain`.
+#[derive(Clone, Debug, Deserialize)]
+pub struct Credentials {
+    /// Connect App key. This is the same as the "key" field of the app
+    /// descriptor JSON file.
+    #[serde(alias = "key")]
+    pub app_key: String,
+
+    /// Connect App shared secret.
+    #[serde(alias = "secret")]
+    pub shared_secret: SharedSecret,
+
+    /// Duration that tokens will be valid for. Defaults to
+    /// [`Signer::DEFAULT_VALID_FOR`].
+    #[serde(
+        default = "default_valid_for",
+        deserialize_with = "deserialize_secs"
+    )]
+    pub valid_for: Duration,
+}
+
+impl Credentials {
+    /// Create a new `Credentials` with the default `valid_for`.
+    pub fn new<S: Into<String>>(
+        app_key: S,
+        shared_secret: SharedSecret,
+    ) -> Credentials {
+        Credentials {
+            app_key: app_key.into(),
+            shared_secret,
+            valid_for: default_valid_for(),
+        }
+    }
+
+    /// Parse credentials from JSON. See [`Credentials`] for the
+    /// format.
+    pub fn from_json(json: &str) -> Result<Credentials, AuthError> {
+        // The serde_json error is not included since its message can
+        // contain part of the input.
+        serde_json::from_str(json).map_err(|err| {
+            store_error(format!(
+                "invalid credentials JSON at line {} column {}",
+                err.line(),
+                err.column()
+            ))
+        })
+    }
+
+    /// Read credentials from a JSON file. See [`Credentials`] for the
+    /// format.
+    pub fn from_json_file<P: AsRef<Path>>(
+        path: P,
+    ) -> Result<Credentials, AuthError> {
+        let path = path.as_ref();
+        let json = fs::read_to_string(path).map_err(|err| {
+            store_error(format!("failed to read {}: {}", path.display(), err))
+        })?;
+        Credentials::from_json(&json)
+    }
+
+    /// Read credentials from the `ATLASSIAN_APP_KEY` and
+    /// `ATLASSIAN_SHARED_SECRET` environment variables. If
+    /// `ATLASSIAN_VALID_FOR` is set, it is parsed as a number of
+    /// seconds.
+    pub fn from_env() -> Result<Credentials, AuthError> {
+        Credentials::from_vars(|name| env::var(name).ok())
+    }
+
+    fn from_vars(
+        var: impl Fn(&str) -> Option<String>,
+    ) -> Result<Credentials, AuthError> {
+        let required = |name: &str| {
+            var(name).ok_or_else(|| store_error(format!("{} is not set", name)))
+        };
+        let mut creds = Credentials::new(
+            required("ATLASSIAN_APP_KEY")?,
+            required("ATLASSIAN_SHARED_SECRET")?.into(),
+        );
+        if let Some(valid_for) = var("ATLASSIAN_VALID_FOR") {
+            let secs = valid_for.parse().map_err(|_| {
+                store_error("ATLASSIAN_VALID_FOR is not a number of seconds")
+            })?;
+            creds.valid_for = Duration::from_secs(secs);
+        }
+        Ok(creds)
+    }
+
+    /// Read credentials from the operating system's keychain: the
+    /// macOS Keychain, the Windows Credential Manager, or the Linux
+    /// kernel keyring. The password of the entry for `service` and
+    /// `user` must contain JSON credentials; see [`Credentials`] for
+    /// the format.
+    #[cfg(feature = "keychain")]
+    pub fn from_keychain(
+        service: &str,
+        user: &str,
+    ) -> Result<Credentials, AuthError> {
+        let entry = keyring::Entry::new(service, user).map_err(|err| {
+            store_error(format!("invalid keychain entry: {}", err))
+        })?;
+        Credentials::from_keychain_entry(&entry)
+    }
+
+    #[cfg(feature = "keychain")]
+    fn from_keychain_entry(
+        entry: &keyring::Entry,
+    ) -> Result<Credentials, AuthError> {
+        let json = entry.get_password().map_err(|err| {
+            store_error(format!("failed to read keychain entry: {}", err))
+        })?;
+        Credentials::from_json(&json)
+    }
+
+    /// Create a [`Figment`] that loads credentials from, in increasing
+    /// order of priority:
+    /// * the `Atlassian.toml` file in the current directory, if it
+    ///   exists
+    /// * the `ATLASSIAN_APP_KEY`, `ATLASSIAN_SHARED_SECRET`, and
+    ///   `ATLASSIAN_VALID_FOR` environment variables (see
+    ///   [`Credentials::from_env`])
+    ///
+    /// More providers can be merged in before calling
+    /// [`Credentials::from_figment`], and `Credentials` can also be
+    /// extracted as part of a larger configuration struct:
+    ///
+    /// ```
+    /// use atlassian_app_auth::Credentials;
+    /// use figment::providers::{Format, Toml};
+    ///
+    /// let figment = Credentials::figment().merge(Toml::string(
+    ///     r#"
+    ///     app_key = "com.example.app"
+    ///     shared_secret = "secret"
+    ///     "#,
+    /// ));
+    /// let creds = Credentials::from_figment(&figment).unwrap();
+    /// assert_eq!(creds.app_key, "com.example.app");
+    /// ```
+    ///
+    /// [`Figment`]: figment::Figment
+    #[cfg(feature = "figment")]
+    pub fn figment() -> figment::Figment {
+        use figment::providers::{Env, Format, Toml};
+
+        figment::Figment::new()
+            .merge(Toml::file("Atlassian.toml"))
+            .merge(Env::prefixed("ATLASSIAN_").only(&[
+                "app_key",
+                "shared_secret",
+                "valid_for",
+            ]))
+    }
+
+    /// Extract credentials from `figment`. The fields are the same as
+    /// for [`Credentials::from_json`].
+    ///
+    /// Like [`Credentials::from_json`], the error does not include the
+    /// values in the configuration.
+    #[cfg(feature = "figment")]
+    pub fn from_figment(
+        figment: &figment::Figment,
+    ) -> Result<Credentials, AuthError> {
+        figment.extract().map_err(|err| {
+            let message = match &err.kind {
+                figment::error::Kind::MissingField(field) => {
+                    format!("missing credentials field `{}`", field)
+                }
+                _ => format!(
+                    "invalid credentials field `{}`",
+                    err.path.join(".")
+                ),
+            };
+            store_error(message)
+        })
+    }
+
+    /// Create a [`Signer`] from the credentials.
+    pub fn signer(&self) -> Signer {
+        Signer::new(self.app_key.clone(), self.shared_secret.clone())
+            .with_valid_for(self.valid_for)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_deserialize() {
+        let creds: Credentials = serde_json::from_str(
+            r#"{"app_key": "app", "shared_secret": "secret", "valid_for": 60}"#,
+        )
+        .unwrap();
+        assert_eq!(creds.app_key, "app");
+        assert_eq!(creds.shared_secret.expose(), "secret");
+        assert_eq!(creds.valid_for, Duration::from_secs(60));
+
+        let creds: Credentials =
+            serde_json::from_str(r#"{"key": "app", "secret": "secret"}"#)
+                .unwrap();
+        assert_eq!(creds.app_key, "app");
+        assert_eq!(creds.shared_secret.expose(), "secret");
+        assert_eq!(creds.valid_for, Signer::DEFAULT_VALID_FOR);
+    }
+
+    #[test]
+    fn test_from_json() {
+        let creds =
+            Credentials::from_json(r#"{"key": "app", "secret": "secret"}"#)
+                .unwrap();
+        assert_eq!(creds.app_key, "app");
+
+        let err = Credentials::from_json(r#"{"key": "hunter2"}"#).unwrap_err();
+        assert!(!err.to_string().contains("hunter2"));
+    }
+
+    #[test]
+    fn test_from_vars() {
+        let vars = |valid_for: Option<&'static str>| {
+            move |name: &str| match name {
+                "ATLASSIAN_APP_KEY" => Some("app".to_string()),
+                "ATLASSIAN_SHARED_SECRET" => Some("secret".to_string()),
+                "ATLASSIAN_VALID_FOR" => valid_for.map(String::from),
+                _ => None,
+            }
+        };
+
+        let creds = Credentials::from_vars(vars(None)).unwrap();
+        assert_eq!(creds.app_key, "app");
+        assert_eq!(creds.shared_secret.expose(), "secret");
+        assert_eq!(creds.valid_for, Signer::DEFAULT_VALID_FOR);
+
+        let creds = Credentials::from_vars(vars(Some("60"))).unwrap();
+        assert_eq!(creds.valid_for, Duration::from_secs(60));
+
+        assert!(Credentials::from_vars(vars(Some("soon"))).is_err());
+        assert!(Credentials::from_vars(|_| None).is_err());
+    }
+
+    #[cfg(feature = "keychain")]
+    #[test]
+    fn test_from_keychain_entry() {
+        use keyring::mock::MockCredential;
+
+        let entry = keyring::Entry::new_with_credential(
+            Box::<MockCredential>::default(),
+        );
+        let err = Credentials::from_keychain_entry(&entry).unwrap_err();
+        assert!(err.to_string().contains("failed to read keychain entry"));
+
+        entry
+            .set_password(r#"{"key": "app", "secret": "secret"}"#)
+            .unwrap();
+        let creds = Credentials::from_keychain_entry(&entry).unwrap();
+        assert_eq!(creds.app_key, "app");
+        assert_eq!(creds.shared_secret.expose(), "secret");
+
+        entry.set_password(r#"{"key": "hunter2"}"#).unwrap();
+        let err = Credentials::from_keychain_entry(&entry).unwrap_err();
+        assert!(!err.to_string().contains("hunter2"));
+    }
+
+    #[cfg(feature = "figment")]
+    #[test]
+    fn test_from_figment() {
+        use figment::providers::{Format, Serialized, Toml};
+        use figment::Figment;
+
+        let defaults = Serialized::defaults(serde_json::json!({
+            "app_key": "app",
+            "valid_for": 60,
+        }));
+        let figment = Figment::from(defaults)
+            .merge(Toml::string(r#"shared_secret = "secret""#));
+        let creds = Credentials::from_figment(&figment).unwrap();
+        assert_eq!(creds.app_key, "app");
+        assert_eq!(creds.shared_secret.expose(), "secret");
+        assert_eq!(creds.valid_for, Duration::from_secs(60));
+
+        let figment = Figment::from(Toml::string(
+            r#"
+            [atlassian]
+            key = "app"
+            secret = "secret"
+            "#,
+        ));
+        let creds =
+            Credentials::from_figment(&figment.focus("atlassian")).unwrap();
+        assert_eq!(creds.valid_for, Signer::DEFAULT_VALID_FOR);
+
+        let figment = Figment::from(Toml::string(r#"app_key = "app""#));
+        let err = Credentials::from_figment(&figment).unwrap_err();
+        assert!(err.to_string().contains("shared_secret"));
+
+        let figment = Figment::from(Toml::string(
+            r#"
+            app_key = "app"
+            shared_secret = "hunter2"
+            valid_for = "hunter2"
+            "#,
+        ));
+        let err = Credentials::from_figment(&figment).unwrap_err();
+        assert!(err.to_string().contains("valid_for"));
+        assert!(!err.to_string().contains("hunter2"));
+    }
+}
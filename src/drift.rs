@@ -0,0 +1,168 @@
+use crate::{Clock, SystemClock};
+use std::fmt;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+/// Compares the time in Atlassian's `Date` response headers with the
+/// local clock, and warns when they differ by more than a threshold.
+///
+/// Tokens are rejected if the clocks disagree about whether they have
+/// expired, so clock drift shows up as intermittent 401 responses
+/// that are hard to diagnose. When a check is added to a client with
+/// e.g. [`BlockingTenantClient::with_clock_drift_check`], every
+/// response is checked. Drift beyond the threshold is logged as a
+/// warning (with the `log` or `tracing` feature) and counted in
+/// [`ClockDriftCheck::warnings`].
+///
+/// The `Date` header only has a resolution of one second, and is set
+/// some time before the response is received, so a threshold of less
+/// than a few seconds will produce false positives.
+///
+/// [`BlockingTenantClient::with_clock_drift_check`]: crate::BlockingTenantClient::with_clock_drift_check
+pub struct ClockDriftCheck {
+    threshold: Duration,
+    clock: Arc<dyn Clock>,
+    last_drift: Mutex<Option<i64>>,
+    warnings: AtomicU64,
+}
+
+impl ClockDriftCheck {
+    /// Default threshold for warnings.
+    pub const DEFAULT_THRESHOLD: Duration = Duration::from_secs(30);
+
+    /// Create a new `ClockDriftCheck` that warns when the clocks differ
+    /// by more than `threshold`.
+    pub fn new(threshold: Duration) -> ClockDriftCheck {
+        ClockDriftCheck {
+            threshold,
+            clock: Arc::new(SystemClock),
+            last_drift: Mutex::new(None),
+            warnings: AtomicU64::new(0),
+        }
+    }
+
+    /// Use `clock` to get the local time instead of [`SystemClock`].
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> ClockDriftCheck {
+        self.clock = clock;
+        self
+    }
+
+    /// Compare `server_time` with the local time. Returns the drift in
+    /// seconds, which is positive if the local clock is ahead of the
+    /// server.
+    pub fn observe(&self, server_time: SystemTime) -> i64 {
+        let now = self.clock.now();
+        let drift = match now.duration_since(server_time) {
+            Ok(ahead) => ahead.as_secs() as i64,
+            Err(err) => -(err.duration().as_secs() as i64),
+        };
+        *self.last_drift.lock().unwrap() = Some(drift);
+        if drift.unsigned_abs() > self.threshold.as_secs() {
+            self.warnings.fetch_add(1, Ordering::Relaxed);
+            crate::instrument::clock_drift(drift, self.threshold);
+        }
+        drift
+    }
+
+    /// Compare the time in a `Date` header value with the local time.
+    /// Returns `None` if the value can't be parsed. See
+    /// [`ClockDriftCheck::observe`].
+    pub fn observe_header(&self, date: &str) -> Option<i64> {
+        let server_time = httpdate::parse_http_date(date).ok()?;
+        Some(self.observe(server_time))
+    }
+
+    /// Drift in seconds found by the most recent check, or `None` if
+    /// there hasn't been one.
+    pub fn last_drift(&self) -> Option<i64> {
+        *self.last_drift.lock().unwrap()
+    }
+
+    /// Number of checks that found drift beyond the threshold.
+    pub fn warnings(&self) -> u64 {
+        self.warnings.load(Ordering::Relaxed)
+    }
+}
+
+impl Default for ClockDriftCheck {
+    fn default() -> ClockDriftCheck {
+        ClockDriftCheck::new(ClockDriftCheck::DEFAULT_THRESHOLD)
+    }
+}
+
+impl fmt::Debug for ClockDriftCheck {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("ClockDriftCheck")
+            .field("threshold", &self.threshold)
+            .field("last_drift", &self.last_drift())
+            .field("warnings", &self.warnings())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_server::serve_with_headers;
+    use crate::{RetryPolicy, SharedSecret, Signer, Webhooks};
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn test_clock_drift_check() {
+        // Sun, 06 Nov 1994 08:49:37 GMT
+        let server_time = UNIX_EPOCH + Duration::from_secs(784111777);
+        let check = |offset: i64| {
+            let local = UNIX_EPOCH
+                + Duration::from_secs(
+                    784111777_u64.saturating_add_signed(offset),
+                );
+            ClockDriftCheck::new(Duration::from_secs(30))
+                .with_clock(Arc::new(move || local))
+        };
+
+        let ok = check(5);
+        assert_eq!(ok.last_drift(), None);
+        assert_eq!(ok.observe(server_time), 5);
+        assert_eq!(ok.last_drift(), Some(5));
+        assert_eq!(ok.warnings(), 0);
+
+        let behind = check(-31);
+        assert_eq!(
+            behind.observe_header("Sun, 06 Nov 1994 08:49:37 GMT"),
+            Some(-31)
+        );
+        assert_eq!(behind.warnings(), 1);
+        assert_eq!(behind.observe_header("yesterday"), None);
+        assert_eq!(behind.warnings(), 1);
+    }
+
+    #[test]
+    fn test_client_clock_drift() {
+        let server = serve_with_headers(vec![
+            (
+                503,
+                "Date: Sun, 06 Nov 1994 08:49:37 GMT\r\n",
+                String::new(),
+            ),
+            (200, "", r#"{"isLast": true, "values": []}"#.into()),
+        ]);
+        let drift = Arc::new(ClockDriftCheck::default());
+        let webhooks =
+            Webhooks::new(
+                &server.url,
+                Signer::new("app", SharedSecret::new("secret")),
+            )
+            .with_retry_policy(RetryPolicy::new().with_backoff(
+                Duration::from_millis(1),
+                Duration::from_millis(1),
+            ))
+            .with_clock_drift_check(drift.clone());
+        webhooks.list().unwrap();
+
+        // Error responses are checked too, since drift is a likely
+        // cause of them. Responses without a Date header are ignored.
+        assert!(drift.last_drift().unwrap() > 0);
+        assert_eq!(drift.warnings(), 1);
+    }
+}
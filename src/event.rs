@@ -0,0 +1,138 @@
+use crate::AuthError;
+#[cfg(any(feature = "otel", feature = "prometheus"))]
+use crate::VerificationFailure;
+use std::time::Duration;
+
+/// Type of operation described by an [`AuthEvent`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AuthEventKind {
+    /// A token was created for an outgoing request.
+    Sign,
+
+    /// A token on an incoming request was verified.
+    Verify,
+}
+
+/// Record of a token being created or verified, passed to an
+/// [`AuthEventSink`].
+///
+/// Events never contain the shared secret or the token itself.
+#[derive(Debug)]
+#[non_exhaustive]
+pub struct AuthEvent<'a> {
+    /// Whether a token was created or verified.
+    pub kind: AuthEventKind,
+
+    /// Tenant associated with the request, if known. For verification
+    /// this is the issuer claimed by the token (the tenant's client
+    /// key); if verification failed it has not been authenticated.
+    pub tenant: Option<&'a str>,
+
+    /// HTTP method of the request. This is empty for context tokens
+    /// (see `Verifier::verify_context_token`), which aren't tied to a
+    /// request.
+    pub method: &'a str,
+
+    /// Host of the request URL.
+    pub host: Option<&'a str>,
+
+    /// Path of the request URL. The query string is not included. Like
+    /// `method`, this is empty for context tokens.
+    pub path: &'a str,
+
+    /// Whether the operation succeeded.
+    pub outcome: Result<(), &'a AuthError>,
+
+    /// How long it took to create or verify the token. This is
+    /// measured with a monotonic [`Instant`], not the signer or
+    /// verifier's [`Clock`], so it is unaffected by changes to the
+    /// system time. It is zero if the operation failed before a token
+    /// was created or checked (e.g. because the tenant is unknown), and
+    /// on `wasm32-unknown-unknown`, which has no [`Instant`].
+    ///
+    /// [`Clock`]: crate::Clock
+    /// [`Instant`]: std::time::Instant
+    pub elapsed: Duration,
+}
+
+impl<'a> AuthEvent<'a> {
+    pub(crate) fn new(
+        kind: AuthEventKind,
+        tenant: Option<&'a str>,
+        method: &'a str,
+        host: Option<&'a str>,
+        path: &'a str,
+        outcome: Result<(), &'a AuthError>,
+    ) -> AuthEvent<'a> {
+        AuthEvent {
+            kind,
+            tenant,
+            method,
+            host,
+            path,
+            outcome,
+            elapsed: Duration::ZERO,
+        }
+    }
+
+    pub(crate) fn with_elapsed(mut self, elapsed: Duration) -> AuthEvent<'a> {
+        self.elapsed = elapsed;
+        self
+    }
+}
+
+/// Short label describing the outcome of an operation, used by the
+/// metrics sinks.
+#[cfg(any(feature = "otel", feature = "prometheus"))]
+pub(crate) fn outcome_label(outcome: Result<(), &AuthError>) -> &'static str {
+    let err = match outcome {
+        Ok(()) => return "ok",
+        Err(err) => err,
+    };
+    match err {
+        AuthError::JwtError(_) => "jwt_error",
+        AuthError::InvalidUrl { .. } => "invalid_url",
+        AuthError::InvalidMethod { .. } => "invalid_method",
+        AuthError::SecretRejected { .. } => "secret_rejected",
+        AuthError::InvalidLifetime { .. } => "invalid_lifetime",
+        AuthError::HostNotAllowed { .. } => "host_not_allowed",
+        AuthError::VerificationFailed { reason } => match reason {
+            VerificationFailure::Malformed => "malformed",
+            VerificationFailure::InvalidSignature => "invalid_signature",
+            VerificationFailure::UnsupportedAlgorithm => {
+                "unsupported_algorithm"
+            }
+            VerificationFailure::Expired => "expired",
+            VerificationFailure::NotYetValid => "not_yet_valid",
+            VerificationFailure::InvalidIssuer => "invalid_issuer",
+        },
+        AuthError::QshMismatch { .. } => "qsh_mismatch",
+        AuthError::UnknownTenant => "unknown_tenant",
+        AuthError::StoreError(_) => "store_error",
+        AuthError::RequestError(_) => "request_error",
+        AuthError::ApiError(_) => "api_error",
+        AuthError::TimeError(_) => "time_error",
+    }
+}
+
+#[cfg(any(feature = "otel", feature = "prometheus"))]
+pub(crate) fn operation_label(kind: AuthEventKind) -> &'static str {
+    match kind {
+        AuthEventKind::Sign => "sign",
+        AuthEventKind::Verify => "verify",
+    }
+}
+
+/// Receives an [`AuthEvent`] every time a token is created or
+/// verified. This can be used to centralize audit logging, or to feed
+/// a metrics library (e.g. counting tokens issued and verification
+/// failures by reason, and recording [`AuthEvent::elapsed`] in a
+/// latency histogram).
+///
+/// See [`Signer::with_event_sink`] and `Verifier::with_event_sink`.
+///
+/// [`Signer::with_event_sink`]: crate::Signer::with_event_sink
+pub trait AuthEventSink: Send + Sync {
+    /// Handle an event.
+    fn record(&self, event: &AuthEvent);
+}
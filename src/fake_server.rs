@@ -0,0 +1,84 @@
+//! Minimal HTTP server for tests that need to talk to a remote API.
+
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use url::Url;
+
+/// Request received by a [`FakeServer`].
+pub(crate) struct Request {
+    pub head: String,
+    pub body: String,
+}
+
+pub(crate) struct FakeServer {
+    pub url: Url,
+    pub requests: Arc<Mutex<Vec<Request>>>,
+}
+
+/// Start a server that replies to each incoming request with the next
+/// `(status, body)` pair from `responses`.
+pub(crate) fn serve(responses: Vec<(u16, String)>) -> FakeServer {
+    serve_with_headers(
+        responses
+            .into_iter()
+            .map(|(status, body)| (status, "", body))
+            .collect(),
+    )
+}
+
+/// Same as [`serve`], but each response also has extra header lines,
+/// each ending with `\r\n`.
+pub(crate) fn serve_with_headers(
+    responses: Vec<(u16, &'static str, String)>,
+) -> FakeServer {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let url =
+        Url::parse(&format!("http://{}/", listener.local_addr().unwrap()))
+            .unwrap();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+
+    let requests_clone = requests.clone();
+    thread::spawn(move || {
+        for (status, headers, body) in responses {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut head = String::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" || line.is_empty() {
+                    break;
+                }
+                let lower = line.to_lowercase();
+                if let Some(len) = lower.strip_prefix("content-length:") {
+                    content_length = len.trim().parse().unwrap();
+                }
+                head.push_str(&line);
+            }
+            let mut request_body = vec![0; content_length];
+            reader.read_exact(&mut request_body).unwrap();
+
+            requests_clone.lock().unwrap().push(Request {
+                head,
+                body: String::from_utf8(request_body).unwrap(),
+            });
+
+            write!(
+                stream,
+                "HTTP/1.1 {} X\r\nContent-Type: application/json\r\n{}\
+                 Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                headers,
+                body.len(),
+                body
+            )
+            .unwrap();
+        }
+    });
+
+    FakeServer { url, requests }
+}
@@ -0,0 +1,349 @@
+//! C bindings, for services in other languages that need to create or
+//! verify Atlassian Connect JWTs.
+//!
+//! Build with:
+//!
+//! ```text
+//! cargo rustc --lib --release --features ffi --crate-type cdylib
+//! cbindgen --config cbindgen.toml --output include/atlassian_app_auth.h
+//! ```
+//!
+//! (or `--crate-type staticlib` for a static library). The header is
+//! checked in at `include/atlassian_app_auth.h`; regenerate it with the
+//! second command when this module changes.
+//!
+//! All strings are null-terminated UTF-8. Strings returned by these
+//! functions must be freed with [`atlassian_app_auth_free_string`].
+//! When a function fails, a description of the error can be retrieved
+//! with [`atlassian_app_auth_last_error`].
+
+use crate::{AuthError, SharedSecret, Signer, Verifier};
+use std::cell::RefCell;
+use std::ffi::{CStr, CString};
+use std::os::raw::c_char;
+use std::ptr;
+use std::time::Duration;
+use url::Url;
+
+/// Result of an FFI call.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum AtlassianAppAuthStatus {
+    /// The call succeeded.
+    Ok = 0,
+    /// An argument was null or not valid UTF-8.
+    InvalidArgument = 1,
+    /// The URL could not be parsed or signed.
+    InvalidUrl = 2,
+    /// The token's signature, expiration time, or format is invalid.
+    VerificationFailed = 3,
+    /// The token's query string hash does not match the request.
+    QshMismatch = 4,
+    /// Any other error.
+    Error = 5,
+}
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    // Interior null bytes can't be represented, so drop them.
+    let message = CString::new(message.replace('\0', ""))
+        .expect("null bytes were removed");
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+fn status(err: &AuthError) -> AtlassianAppAuthStatus {
+    match err {
+        AuthError::InvalidUrl { .. } | AuthError::InvalidMethod { .. } => {
+            AtlassianAppAuthStatus::InvalidUrl
+        }
+        AuthError::VerificationFailed { .. } => {
+            AtlassianAppAuthStatus::VerificationFailed
+        }
+        AuthError::QshMismatch { .. } => AtlassianAppAuthStatus::QshMismatch,
+        _ => AtlassianAppAuthStatus::Error,
+    }
+}
+
+fn fail(
+    status: AtlassianAppAuthStatus,
+    message: String,
+) -> AtlassianAppAuthStatus {
+    set_last_error(message);
+    status
+}
+
+/// Convert a C string argument, reporting which argument was invalid.
+///
+/// # Safety
+///
+/// `ptr` must be null or point to a null-terminated string.
+unsafe fn arg<'a>(
+    ptr: *const c_char,
+    name: &str,
+) -> Result<&'a str, AtlassianAppAuthStatus> {
+    if ptr.is_null() {
+        return Err(fail(
+            AtlassianAppAuthStatus::InvalidArgument,
+            format!("{} is null", name),
+        ));
+    }
+    CStr::from_ptr(ptr).to_str().map_err(|_| {
+        fail(
+            AtlassianAppAuthStatus::InvalidArgument,
+            format!("{} is not valid UTF-8", name),
+        )
+    })
+}
+
+fn parse_url(url: &str) -> Result<Url, AtlassianAppAuthStatus> {
+    Url::parse(url).map_err(|err| {
+        fail(
+            AtlassianAppAuthStatus::InvalidUrl,
+            format!("invalid URL: {}", err),
+        )
+    })
+}
+
+/// Store `value` in `*out`, transferring ownership to the caller.
+///
+/// # Safety
+///
+/// `out` must be null or valid for writes.
+unsafe fn output(
+    out: *mut *mut c_char,
+    value: String,
+) -> AtlassianAppAuthStatus {
+    if out.is_null() {
+        return fail(
+            AtlassianAppAuthStatus::InvalidArgument,
+            "out is null".into(),
+        );
+    }
+    match CString::new(value) {
+        Ok(value) => {
+            *out = value.into_raw();
+            AtlassianAppAuthStatus::Ok
+        }
+        Err(_) => fail(
+            AtlassianAppAuthStatus::Error,
+            "output contains a null byte".into(),
+        ),
+    }
+}
+
+/// Create the value of the Authorization header for a request, e.g.
+/// `"JWT eyJ0eXAi..."`, and store it in `*out`.
+///
+/// # Safety
+///
+/// The string arguments must be null or point to null-terminated
+/// strings, and `out` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn atlassian_app_auth_create_auth_header(
+    method: *const c_char,
+    url: *const c_char,
+    app_key: *const c_char,
+    shared_secret: *const c_char,
+    valid_for_secs: u64,
+    out: *mut *mut c_char,
+) -> AtlassianAppAuthStatus {
+    let result = (|| {
+        let method = arg(method, "method")?;
+        let url = parse_url(arg(url, "url")?)?;
+        let signer = Signer::new(
+            arg(app_key, "app_key")?,
+            SharedSecret::new(arg(shared_secret, "shared_secret")?),
+        )
+        .with_valid_for(Duration::from_secs(valid_for_secs));
+        signer
+            .create_auth_header(method, &url)
+            .map_err(|err| fail(status(&err), err.to_string()))
+    })();
+    match result {
+        Ok(header) => output(out, header.value),
+        Err(status) => status,
+    }
+}
+
+/// Create the query string hash ("qsh" claim) for a request and store
+/// it in `*out`.
+///
+/// # Safety
+///
+/// The string arguments must be null or point to null-terminated
+/// strings, and `out` must be null or valid for writes.
+#[no_mangle]
+pub unsafe extern "C" fn atlassian_app_auth_query_string_hash(
+    method: *const c_char,
+    url: *const c_char,
+    out: *mut *mut c_char,
+) -> AtlassianAppAuthStatus {
+    let result = (|| {
+        let method = arg(method, "method")?;
+        let url = parse_url(arg(url, "url")?)?;
+        Ok(crate::create_query_string_hash(method, &url))
+    })();
+    match result {
+        Ok(qsh) => output(out, qsh),
+        Err(status) => status,
+    }
+}
+
+/// Verify a JWT sent by Atlassian. The token should not include the
+/// `"JWT "` prefix.
+///
+/// # Safety
+///
+/// The arguments must be null or point to null-terminated strings.
+#[no_mangle]
+pub unsafe extern "C" fn atlassian_app_auth_verify_token(
+    token: *const c_char,
+    method: *const c_char,
+    url: *const c_char,
+    shared_secret: *const c_char,
+) -> AtlassianAppAuthStatus {
+    let result = (|| {
+        let token = arg(token, "token")?;
+        let method = arg(method, "method")?;
+        let url = parse_url(arg(url, "url")?)?;
+        let verifier = Verifier::new(SharedSecret::new(arg(
+            shared_secret,
+            "shared_secret",
+        )?));
+        verifier
+            .verify(token, method, &url)
+            .map_err(|err| fail(status(&err), err.to_string()))
+    })();
+    match result {
+        Ok(()) => AtlassianAppAuthStatus::Ok,
+        Err(status) => status,
+    }
+}
+
+/// Get a description of the last error on this thread, or null if
+/// there hasn't been one. The string is owned by the library and is
+/// valid until the next call on this thread; it must not be freed.
+#[no_mangle]
+pub extern "C" fn atlassian_app_auth_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |message| message.as_ptr())
+    })
+}
+
+/// Free a string returned by this library. Passing null does nothing.
+///
+/// # Safety
+///
+/// `s` must be null or a string returned by this library that has not
+/// already been freed.
+#[no_mangle]
+pub unsafe extern "C" fn atlassian_app_auth_free_string(s: *mut c_char) {
+    if !s.is_null() {
+        drop(CString::from_raw(s));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn c(s: &str) -> CString {
+        CString::new(s).unwrap()
+    }
+
+    unsafe fn take(s: *mut c_char) -> String {
+        let value = CStr::from_ptr(s).to_str().unwrap().to_string();
+        atlassian_app_auth_free_string(s);
+        value
+    }
+
+    unsafe fn last_error() -> String {
+        CStr::from_ptr(atlassian_app_auth_last_error())
+            .to_str()
+            .unwrap()
+            .to_string()
+    }
+
+    #[test]
+    fn test_ffi() {
+        let url = c("https://example.com/example?a=1");
+        let get = c("GET");
+        let secret = c("secret");
+        unsafe {
+            let mut out = ptr::null_mut();
+            assert_eq!(
+                atlassian_app_auth_create_auth_header(
+                    get.as_ptr(),
+                    url.as_ptr(),
+                    c("app").as_ptr(),
+                    secret.as_ptr(),
+                    60,
+                    &mut out,
+                ),
+                AtlassianAppAuthStatus::Ok
+            );
+            let header = take(out);
+            let token = c(header.strip_prefix("JWT ").unwrap());
+
+            assert_eq!(
+                atlassian_app_auth_verify_token(
+                    token.as_ptr(),
+                    get.as_ptr(),
+                    url.as_ptr(),
+                    secret.as_ptr(),
+                ),
+                AtlassianAppAuthStatus::Ok
+            );
+            assert_eq!(
+                atlassian_app_auth_verify_token(
+                    token.as_ptr(),
+                    c("POST").as_ptr(),
+                    url.as_ptr(),
+                    secret.as_ptr(),
+                ),
+                AtlassianAppAuthStatus::QshMismatch
+            );
+            assert!(last_error().starts_with("query string hash mismatch"));
+
+            let mut out = ptr::null_mut();
+            assert_eq!(
+                atlassian_app_auth_query_string_hash(
+                    get.as_ptr(),
+                    url.as_ptr(),
+                    &mut out
+                ),
+                AtlassianAppAuthStatus::Ok
+            );
+            assert_eq!(
+                take(out),
+                crate::create_query_string_hash(
+                    "GET",
+                    &Url::parse("https://example.com/example?a=1").unwrap()
+                )
+            );
+
+            assert_eq!(
+                atlassian_app_auth_query_string_hash(
+                    ptr::null(),
+                    url.as_ptr(),
+                    &mut out
+                ),
+                AtlassianAppAuthStatus::InvalidArgument
+            );
+            assert_eq!(last_error(), "method is null");
+            assert_eq!(
+                atlassian_app_auth_query_string_hash(
+                    get.as_ptr(),
+                    c("not a url").as_ptr(),
+                    &mut out
+                ),
+                AtlassianAppAuthStatus::InvalidUrl
+            );
+        }
+    }
+}
@@ -0,0 +1,232 @@
+use crate::{store_error, AuthError, Credentials, SharedSecret, Signer};
+use serde::Deserialize;
+use std::env;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use url::Url;
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: SharedSecret,
+    expires_in: u64,
+}
+
+#[derive(Deserialize)]
+struct AccessResponse {
+    payload: Payload,
+}
+
+#[derive(Deserialize)]
+struct Payload {
+    data: String,
+}
+
+/// Client for loading credentials from Google Cloud Secret Manager.
+///
+/// The secret must contain JSON [`Credentials`].
+///
+/// [`Credentials`]: crate::Credentials
+///
+/// Unless an access token is provided with
+/// [`GcpSecretManager::with_access_token`], a token for the default
+/// service account is requested from the metadata server. This works
+/// on Compute Engine, Cloud Run, GKE, and other GCP runtimes.
+///
+/// This uses a blocking HTTP client, so it should not be called
+/// directly from async code.
+#[derive(Debug)]
+pub struct GcpSecretManager {
+    http: reqwest::blocking::Client,
+    project: String,
+    endpoint: Url,
+    metadata_endpoint: Url,
+    access_token: Mutex<Option<(SharedSecret, Option<Instant>)>>,
+}
+
+impl GcpSecretManager {
+    /// Create a new `GcpSecretManager` for secrets in `project`.
+    pub fn new<S: Into<String>>(project: S) -> GcpSecretManager {
+        // The metadata server host can be overridden with the same
+        // environment variable that Google's client libraries use.
+        let metadata_host = env::var("GCE_METADATA_HOST")
+            .unwrap_or_else(|_| "metadata.google.internal".into());
+        GcpSecretManager {
+            http: reqwest::blocking::Client::new(),
+            project: project.into(),
+            endpoint: Url::parse("https://secretmanager.googleapis.com/")
+                .unwrap(),
+            metadata_endpoint: Url::parse(&format!(
+                "http://{}/",
+                metadata_host
+            ))
+            .unwrap_or_else(|_| {
+                Url::parse("http://metadata.google.internal/").unwrap()
+            }),
+            access_token: Mutex::new(None),
+        }
+    }
+
+    /// Use `token` to authenticate instead of requesting one from the
+    /// metadata server.
+    pub fn with_access_token(self, token: SharedSecret) -> GcpSecretManager {
+        *self.access_token.lock().unwrap() = Some((token, None));
+        self
+    }
+
+    /// Override the Secret Manager endpoint.
+    pub fn with_endpoint(mut self, endpoint: Url) -> GcpSecretManager {
+        self.endpoint = endpoint;
+        self
+    }
+
+    /// Override the metadata server endpoint.
+    pub fn with_metadata_endpoint(mut self, endpoint: Url) -> GcpSecretManager {
+        self.metadata_endpoint = endpoint;
+        self
+    }
+
+    fn access_token(&self) -> Result<SharedSecret, AuthError> {
+        let mut cached = self.access_token.lock().unwrap();
+        if let Some((token, expires)) = &*cached {
+            let fresh = match expires {
+                Some(expires) => Instant::now() < *expires,
+                None => true,
+            };
+            if fresh {
+                return Ok(token.clone());
+            }
+        }
+
+        let url = self
+            .metadata_endpoint
+            .join("computeMetadata/v1/instance/service-accounts/default/token")
+            .map_err(store_error)?;
+        let resp: TokenResponse = self
+            .http
+            .get(url)
+            .header("Metadata-Flavor", "Google")
+            .send()
+            .and_then(|resp| resp.error_for_status())
+            .and_then(|resp| resp.json())
+            .map_err(store_error)?;
+
+        // Refresh a minute early to avoid using a token that expires
+        // in flight.
+        let lifetime = Duration::from_secs(resp.expires_in.saturating_sub(60));
+        *cached =
+            Some((resp.access_token.clone(), Some(Instant::now() + lifetime)));
+        Ok(resp.access_token)
+    }
+
+    /// Get the value of a secret. `secret` is either the short name of
+    /// a secret in the project (in which case the latest version is
+    /// used), or a full version resource name such as
+    /// `projects/my-project/secrets/my-secret/versions/3`.
+    pub fn get_secret_string(
+        &self,
+        secret: &str,
+    ) -> Result<SharedSecret, AuthError> {
+        let name = if secret.contains('/') {
+            secret.to_string()
+        } else {
+            format!(
+                "projects/{}/secrets/{}/versions/latest",
+                self.project, secret
+            )
+        };
+        let url = self
+            .endpoint
+            .join(&format!("v1/{}:access", name))
+            .map_err(store_error)?;
+
+        let resp: AccessResponse = self
+            .http
+            .get(url)
+            .bearer_auth(self.access_token()?.expose())
+            .send()
+            .and_then(|resp| resp.error_for_status())
+            .and_then(|resp| resp.json())
+            .map_err(store_error)?;
+
+        let data = base64::decode(&resp.payload.data).map_err(store_error)?;
+        let data = String::from_utf8(data)
+            .map_err(|_| store_error("secret is not valid UTF-8"))?;
+        Ok(SharedSecret::new(data))
+    }
+
+    /// Read [`Credentials`] from the JSON secret identified by
+    /// `secret`. See [`GcpSecretManager::get_secret_string`] for the
+    /// format of `secret`.
+    pub fn credentials(&self, secret: &str) -> Result<Credentials, AuthError> {
+        Credentials::from_json(self.get_secret_string(secret)?.expose())
+    }
+
+    /// Create a [`Signer`] from the JSON secret identified by
+    /// `secret`.
+    pub fn signer(&self, secret: &str) -> Result<Signer, AuthError> {
+        Ok(self.credentials(secret)?.signer())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_server::serve;
+
+    fn access_response(data: &str) -> String {
+        serde_json::json!({
+            "name": "projects/123/secrets/atlassian/versions/1",
+            "payload": { "data": base64::encode(data) },
+        })
+        .to_string()
+    }
+
+    #[test]
+    fn test_signer() {
+        let server = serve(vec![
+            (
+                200,
+                r#"{"access_token": "token", "expires_in": 3599, "token_type": "Bearer"}"#
+                    .into(),
+            ),
+            (200, access_response(r#"{"key": "app", "secret": "secret"}"#)),
+            (200, access_response(r#"{"key": "app", "secret": "secret"}"#)),
+        ]);
+        let client = GcpSecretManager::new("my-project")
+            .with_endpoint(server.url.clone())
+            .with_metadata_endpoint(server.url.clone());
+
+        // The access token is only requested once.
+        for _ in 0..2 {
+            assert_eq!(client.signer("atlassian").unwrap().app_key(), "app");
+        }
+
+        let requests = server.requests.lock().unwrap();
+        assert_eq!(requests.len(), 3);
+        assert!(requests[0].head.starts_with(
+            "GET /computeMetadata/v1/instance/service-accounts/default/token"
+        ));
+        assert!(requests[0].head.contains("metadata-flavor: Google"));
+        assert!(requests[1].head.starts_with(
+            "GET /v1/projects/my-project/secrets/atlassian/versions/latest:access"
+        ));
+        assert!(requests[1].head.contains("authorization: Bearer token"));
+        assert!(requests.iter().all(|request| request.body.is_empty()));
+    }
+
+    #[test]
+    fn test_resource_name() {
+        let server = serve(vec![(200, access_response("value"))]);
+        let client = GcpSecretManager::new("my-project")
+            .with_access_token("token".into())
+            .with_endpoint(server.url.clone());
+
+        let secret = client
+            .get_secret_string("projects/other/secrets/s/versions/2")
+            .unwrap();
+        assert_eq!(secret.expose(), "value");
+        assert!(server.requests.lock().unwrap()[0]
+            .head
+            .starts_with("GET /v1/projects/other/secrets/s/versions/2:access"));
+    }
+}
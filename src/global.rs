@@ -0,0 +1,76 @@
+use crate::{AuthError, Credentials, Header, Signer};
+use once_cell::sync::OnceCell;
+use url::Url;
+
+static SIGNER: OnceCell<Signer> = OnceCell::new();
+
+/// Set the credentials used by [`sign`].
+///
+/// This is a convenience for single-tenant tools, which would
+/// otherwise pass a [`Signer`] to every function that sends a request.
+/// Libraries and multi-tenant apps should use a [`Signer`] or
+/// [`TenantAuth`] instead.
+///
+/// The credentials can only be set once; if they are already set,
+/// `credentials` is returned as the error.
+///
+/// ```
+/// use atlassian_app_auth::{init_global, sign, Credentials, SharedSecret};
+/// use url::Url;
+///
+/// init_global(Credentials::new("my-app", SharedSecret::new("secret")))
+///     .unwrap();
+///
+/// let url = Url::parse("https://example.atlassian.net/rest/api/3/myself").unwrap();
+/// let header = sign("GET", &url).unwrap();
+/// ```
+///
+/// [`TenantAuth`]: crate::TenantAuth
+pub fn init_global(credentials: Credentials) -> Result<(), Credentials> {
+    let mut credentials = Some(credentials);
+    SIGNER.get_or_init(|| {
+        credentials
+            .take()
+            .expect("credentials are only taken once")
+            .signer()
+    });
+    match credentials {
+        None => Ok(()),
+        Some(credentials) => Err(credentials),
+    }
+}
+
+/// Create an auth header for a request with the credentials set by
+/// [`init_global`]. See [`Signer::create_auth_header`].
+///
+/// # Panics
+///
+/// Panics if [`init_global`] has not been called.
+pub fn sign(method: &str, url: &Url) -> Result<Header, AuthError> {
+    SIGNER
+        .get()
+        .expect("init_global must be called before sign")
+        .create_auth_header(method, url)
+}
+
+#[cfg(all(test, feature = "verify"))]
+mod tests {
+    use super::*;
+    use crate::{SharedSecret, Verifier};
+
+    #[test]
+    fn test_global_signer() {
+        let secret = SharedSecret::new("secret");
+        init_global(Credentials::new("app", secret.clone())).unwrap();
+        let other = Credentials::new("other", SharedSecret::new("other"));
+        assert_eq!(init_global(other).unwrap_err().app_key, "other");
+
+        let url = Url::parse("https://example.com/rest/api/3/myself").unwrap();
+        let header = sign("GET", &url).unwrap();
+        let token = header.value.strip_prefix("JWT ").unwrap();
+        let claims = Verifier::new(secret)
+            .verify_claims(token, "GET", &url)
+            .unwrap();
+        assert_eq!(claims.iss, "app");
+    }
+}
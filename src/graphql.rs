@@ -0,0 +1,213 @@
+use crate::retry::send;
+use crate::url_builder::GATEWAY_URL;
+use crate::{
+    create_bearer_auth_header, request_error, ApiError, AuthError, RetryPolicy,
+    SharedSecret,
+};
+use reqwest::header::{ACCEPT, CONTENT_TYPE};
+use reqwest::Method;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use url::Url;
+
+#[derive(Serialize)]
+struct Request<'a, V: ?Sized> {
+    query: &'a str,
+    variables: &'a V,
+}
+
+#[derive(Deserialize)]
+struct Response<T> {
+    data: Option<T>,
+    #[serde(default)]
+    errors: Vec<GraphQlError>,
+}
+
+#[derive(Deserialize)]
+struct GraphQlError {
+    message: String,
+    #[serde(default)]
+    extensions: Extensions,
+}
+
+#[derive(Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Extensions {
+    status_code: Option<u16>,
+    error_type: Option<String>,
+    classification: Option<String>,
+}
+
+/// Convert the errors in a GraphQL response to an [`ApiError`]. The
+/// status and code are taken from the first error that has them, since
+/// the HTTP status of the response is usually 200.
+fn to_api_error(status: u16, errors: Vec<GraphQlError>) -> ApiError {
+    let mut api_error = ApiError {
+        status,
+        ..ApiError::default()
+    };
+    let mut status_code = None;
+    for error in errors {
+        status_code = status_code.or(error.extensions.status_code);
+        if api_error.code.is_none() {
+            api_error.code = error
+                .extensions
+                .error_type
+                .or(error.extensions.classification);
+        }
+        api_error.messages.push(error.message);
+    }
+    api_error.status = status_code.unwrap_or(status);
+    api_error
+}
+
+/// Sends queries to the Atlassian GraphQL gateway
+/// (`https://api.atlassian.com/graphql`).
+///
+/// The gateway only accepts OAuth 2.0 access tokens, not Connect JWTs,
+/// so each request is authenticated with a bearer token. The token must
+/// have been granted for the `api.atlassian.com` audience (see
+/// [`oauth_authorize_url`]) with the scopes needed by the query's
+/// fields. A query that needs a missing scope fails with an
+/// [`AuthError::ApiError`] whose status is 403.
+///
+/// Errors in the response are returned as an [`AuthError::ApiError`],
+/// even if the response also has partial data.
+///
+/// This uses a blocking HTTP client, so it should not be called
+/// directly from async code.
+///
+/// ```no_run
+/// use atlassian_app_auth::{GraphQlClient, SharedSecret};
+/// use serde_json::{json, Value};
+///
+/// let client = GraphQlClient::new();
+/// let token = SharedSecret::new("access-token");
+/// let data: Value = client
+///     .query(&token, "query { me { user { name } } }", &json!({}))
+///     .unwrap();
+/// ```
+///
+/// [`oauth_authorize_url`]: crate::oauth_authorize_url
+#[derive(Debug)]
+pub struct GraphQlClient {
+    http: reqwest::blocking::Client,
+    endpoint: Url,
+    retry_policy: RetryPolicy,
+}
+
+impl GraphQlClient {
+    /// Create a new `GraphQlClient`.
+    pub fn new() -> GraphQlClient {
+        let endpoint = Url::parse(GATEWAY_URL)
+            .and_then(|url| url.join("graphql"))
+            .expect("GraphQL URL is not valid");
+        GraphQlClient {
+            http: reqwest::blocking::Client::new(),
+            endpoint,
+            retry_policy: RetryPolicy::default(),
+        }
+    }
+
+    /// Override the endpoint URL.
+    pub fn with_endpoint(mut self, endpoint: Url) -> GraphQlClient {
+        self.endpoint = endpoint;
+        self
+    }
+
+    /// Retry failed requests according to `retry_policy` instead of
+    /// [`RetryPolicy::default`].
+    pub fn with_retry_policy(
+        mut self,
+        retry_policy: RetryPolicy,
+    ) -> GraphQlClient {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    /// Send `query` with `variables`, authenticated with
+    /// `access_token`, and parse the `data` of the response.
+    pub fn query<V: Serialize + ?Sized, T: DeserializeOwned>(
+        &self,
+        access_token: &SharedSecret,
+        query: &str,
+        variables: &V,
+    ) -> Result<T, AuthError> {
+        let body = serde_json::to_vec(&Request { query, variables })
+            .map_err(request_error)?;
+        let header = create_bearer_auth_header(access_token);
+        let resp = send(&self.retry_policy, None, &Method::POST, || {
+            Ok(self
+                .http
+                .post(self.endpoint.clone())
+                .header(header.name, &header.value)
+                .header(ACCEPT, "application/json")
+                .header(CONTENT_TYPE, "application/json")
+                .body(body.clone()))
+        })?;
+        let status = resp.status().as_u16();
+        let resp: Response<T> = resp.json().map_err(request_error)?;
+        if !resp.errors.is_empty() {
+            return Err(to_api_error(status, resp.errors).into());
+        }
+        resp.data
+            .ok_or_else(|| request_error("GraphQL response has no data"))
+    }
+}
+
+impl Default for GraphQlClient {
+    fn default() -> GraphQlClient {
+        GraphQlClient::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_server::serve;
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_graphql_client() {
+        let server = serve(vec![
+            (200, r#"{"data": {"me": {"name": "A"}}}"#.into()),
+            (
+                200,
+                json!({
+                    "data": null,
+                    "errors": [{
+                        "message": "missing scope read:me",
+                        "extensions": {
+                            "statusCode": 403,
+                            "errorType": "FORBIDDEN",
+                        },
+                    }],
+                })
+                .to_string(),
+            ),
+        ]);
+        let client = GraphQlClient::new()
+            .with_endpoint(server.url.join("graphql").unwrap());
+        let token = SharedSecret::new("token");
+        let query = "query($id: ID!) { me { name } }";
+        let variables = json!({"id": "1"});
+
+        let data: Value = client.query(&token, query, &variables).unwrap();
+        assert_eq!(data["me"]["name"], "A");
+
+        match client.query::<_, Value>(&token, query, &variables) {
+            Err(AuthError::ApiError(err)) => {
+                assert_eq!(err.status, 403);
+                assert_eq!(err.code.as_deref(), Some("FORBIDDEN"));
+                assert_eq!(err.messages, ["missing scope read:me"]);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let requests = server.requests.lock().unwrap();
+        assert!(requests[0].head.starts_with("POST /graphql HTTP/1.1"));
+        assert!(requests[0].head.contains("authorization: Bearer token"));
+        let body: Value = serde_json::from_str(&requests[0].body).unwrap();
+        assert_eq!(body, json!({"query": query, "variables": variables}));
+    }
+}
@@ -0,0 +1,128 @@
+use crate::client::SiteClient;
+use crate::{AuthError, Credentials, UrlBuilder};
+use url::Url;
+
+/// Result of [`verify_credentials`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum CredentialsStatus {
+    /// The site accepted a request signed with the credentials.
+    Ok,
+
+    /// The site rejected the token (401). The shared secret is wrong,
+    /// or the app key is not one the site knows.
+    InvalidSecret,
+
+    /// The site doesn't have the app installed (404).
+    AppNotInstalled,
+
+    /// The site could not be reached. The string describes the
+    /// connection error.
+    Network(String),
+}
+
+/// Check that `creds` work for the site at `base_url` by sending one
+/// signed request for the app's own descriptor, using the
+/// `rest/atlassian-connect/1/addons/{key}` endpoint.
+///
+/// This can be used to validate a tenant's shared secret at startup
+/// or after an install event. Responses other than the ones described
+/// by [`CredentialsStatus`] are returned as errors.
+///
+/// This uses a blocking HTTP client, so it should not be called
+/// directly from async code.
+///
+/// ```no_run
+/// use atlassian_app_auth::{
+///     verify_credentials, Credentials, CredentialsStatus, SharedSecret,
+/// };
+/// use url::Url;
+///
+/// let creds = Credentials::new("my-app", SharedSecret::new("secret"));
+/// let base = Url::parse("https://example.atlassian.net").unwrap();
+/// match verify_credentials(&creds, &base).unwrap() {
+///     CredentialsStatus::Ok => {}
+///     status => eprintln!("credentials don't work: {:?}", status),
+/// }
+/// ```
+pub fn verify_credentials(
+    creds: &Credentials,
+    base_url: &Url,
+) -> Result<CredentialsStatus, AuthError> {
+    let site = SiteClient::new(base_url, creds.signer());
+    let url = UrlBuilder::new(base_url)
+        .api("rest/atlassian-connect/1")
+        .path("addons")
+        .segment(&creds.app_key)
+        .build()?;
+    let err = match site.execute::<()>("GET", url, None) {
+        Ok(_) => return Ok(CredentialsStatus::Ok),
+        Err(err) => err,
+    };
+
+    let status = match &err {
+        AuthError::ApiError(api_error) => match api_error.status {
+            401 => CredentialsStatus::InvalidSecret,
+            404 => CredentialsStatus::AppNotInstalled,
+            _ => return Err(err),
+        },
+        AuthError::RequestError(inner) => {
+            match inner.downcast_ref::<reqwest::Error>() {
+                Some(inner) if inner.is_connect() || inner.is_timeout() => {
+                    CredentialsStatus::Network(inner.to_string())
+                }
+                _ => return Err(err),
+            }
+        }
+        _ => return Err(err),
+    };
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_server::serve;
+    use crate::SharedSecret;
+    use std::net::TcpListener;
+
+    #[test]
+    fn test_verify_credentials() {
+        let server = serve(vec![
+            (200, r#"{"key": "app"}"#.into()),
+            (401, String::new()),
+            (404, String::new()),
+            (400, r#"{"errorMessages": ["Bad request"]}"#.into()),
+        ]);
+        let creds = Credentials::new("app", SharedSecret::new("secret"));
+
+        let check = || verify_credentials(&creds, &server.url);
+        assert_eq!(check().unwrap(), CredentialsStatus::Ok);
+        assert_eq!(check().unwrap(), CredentialsStatus::InvalidSecret);
+        assert_eq!(check().unwrap(), CredentialsStatus::AppNotInstalled);
+        match check() {
+            Err(AuthError::ApiError(err)) => {
+                assert_eq!(err.status, 400);
+                assert_eq!(err.messages, ["Bad request"]);
+            }
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        let requests = server.requests.lock().unwrap();
+        assert_eq!(
+            requests[0].head.lines().next().unwrap(),
+            "GET /rest/atlassian-connect/1/addons/app HTTP/1.1"
+        );
+
+        // Nothing is listening on a port after its listener is dropped.
+        let addr = TcpListener::bind("127.0.0.1:0")
+            .unwrap()
+            .local_addr()
+            .unwrap();
+        let url = Url::parse(&format!("http://{}/", addr)).unwrap();
+        assert!(matches!(
+            verify_credentials(&creds, &url).unwrap(),
+            CredentialsStatus::Network(_)
+        ));
+    }
+}
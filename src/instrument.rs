@@ -0,0 +1,110 @@
+//! Diagnostics emitted when the `tracing` or `log` features are
+//! enabled. Log records use the `atlassian_app_auth` target.
+//!
+//! Nothing here ever records the shared secret or a token.
+
+#![cfg_attr(
+    not(any(feature = "tracing", feature = "log")),
+    allow(unused_variables)
+)]
+
+use crate::AuthError;
+#[cfg(feature = "client")]
+use std::time::Duration;
+
+#[cfg(feature = "log")]
+const TARGET: &str = "atlassian_app_auth";
+
+/// Create a span covering the creation of a token.
+#[cfg(feature = "tracing")]
+pub(crate) fn sign_span(method: &str, path: &str) -> tracing::Span {
+    tracing::debug_span!("sign", method, path)
+}
+
+/// Create a span covering the verification of a token.
+#[cfg(all(feature = "tracing", feature = "verify"))]
+pub(crate) fn verify_span(method: &str, path: &str) -> tracing::Span {
+    tracing::debug_span!("verify", method, path)
+}
+
+/// Create a span covering the verification of a context token.
+#[cfg(all(feature = "tracing", feature = "verify"))]
+pub(crate) fn verify_context_span() -> tracing::Span {
+    tracing::debug_span!("verify_context")
+}
+
+pub(crate) fn qsh_computed(qsh: &str) {
+    #[cfg(feature = "tracing")]
+    tracing::trace!(qsh, "computed query string hash");
+    #[cfg(feature = "log")]
+    log::trace!(target: TARGET, "computed query string hash: {}", qsh);
+}
+
+pub(crate) fn token_created(app_key: &str, iat: u64, exp: u64) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(app_key, iat, exp, "created token");
+    #[cfg(feature = "log")]
+    log::debug!(
+        target: TARGET,
+        "created token for {} (iat={}, exp={})",
+        app_key,
+        iat,
+        exp
+    );
+}
+
+#[cfg(feature = "verify")]
+pub(crate) fn token_verified(issuer: &str) {
+    #[cfg(feature = "tracing")]
+    tracing::debug!(issuer, "verified token");
+    #[cfg(feature = "log")]
+    log::debug!(target: TARGET, "verified token from {}", issuer);
+}
+
+pub(crate) fn signing_failed(err: &AuthError) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(error = %err, "failed to create token");
+    #[cfg(feature = "log")]
+    log::warn!(target: TARGET, "failed to create token: {}", err);
+}
+
+#[cfg(feature = "verify")]
+pub(crate) fn verification_failed(err: &AuthError) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(error = %err, "token verification failed");
+    #[cfg(feature = "log")]
+    log::warn!(target: TARGET, "token verification failed: {}", err);
+
+    // The canonical request is logged separately at a lower level
+    // since it includes the request's query parameters.
+    if let AuthError::QshMismatch {
+        canonical_request, ..
+    } = err
+    {
+        #[cfg(feature = "tracing")]
+        tracing::debug!(canonical_request, "query string hash mismatch");
+        #[cfg(feature = "log")]
+        log::debug!(
+            target: TARGET,
+            "query string hash mismatch, canonical request: {}",
+            canonical_request
+        );
+    }
+}
+
+#[cfg(feature = "client")]
+pub(crate) fn clock_drift(drift_secs: i64, threshold: Duration) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(
+        drift_secs,
+        threshold_secs = threshold.as_secs(),
+        "local clock differs from server"
+    );
+    #[cfg(feature = "log")]
+    log::warn!(
+        target: TARGET,
+        "local clock differs from server by {}s (threshold {}s)",
+        drift_secs,
+        threshold.as_secs()
+    );
+}
@@ -3,6 +3,17 @@
 //!
 //! See [examples/request.rs] for a full usage example.
 //!
+//! JWTs that Atlassian sends to the app can be checked with
+//! `verify_token`. Verification is behind the `verify` feature,
+//! which is enabled by default; apps that only sign requests can
+//! disable default features to leave out the verification API. This
+//! does not shrink the dependency tree much: `jsonwebtoken`, which is
+//! always used to decode tokens, depends on `ring` itself.
+//!
+//! The query string hash and token encoding are implemented in the
+//! [atlassian-app-auth-core] crate, which only needs `alloc`, for
+//! targets that can't use this crate.
+//!
 //! Note that the query string hash implementation is incomplete; there
 //! are a lot of special cases that are not yet handled.
 //!
@@ -12,55 +23,212 @@
 //! - <https://developer.atlassian.com/cloud/jira/platform/security-for-connect-apps>
 //! - <https://developer.atlassian.com/cloud/jira/platform/understanding-jwt>
 //!
+//! [atlassian-app-auth-core]: https://docs.rs/atlassian-app-auth-core
 //! [examples/request.rs]: https://github.com/nicholasbishop/atlassian-app-auth/blob/main/examples/request.rs
 
 #![warn(missing_docs)]
 
-use percent_encoding::{utf8_percent_encode, AsciiSet, CONTROLS};
-use serde::Serialize;
-use sha2::Digest;
+mod api_error;
+#[cfg(feature = "aws")]
+mod aws;
+#[cfg(feature = "client")]
+mod client;
+mod clock;
+pub mod compat;
+mod credentials;
+#[cfg(feature = "client")]
+mod drift;
+mod event;
+#[cfg(all(
+    test,
+    any(
+        feature = "aws",
+        feature = "client",
+        feature = "gcp",
+        feature = "vault"
+    )
+))]
+mod fake_server;
+#[cfg(feature = "ffi")]
+mod ffi;
+#[cfg(feature = "gcp")]
+mod gcp;
+#[cfg(feature = "global")]
+mod global;
+#[cfg(feature = "client")]
+mod graphql;
+#[cfg(feature = "client")]
+mod health;
+mod instrument;
+#[cfg(feature = "prometheus")]
+mod metrics;
+#[cfg(feature = "otel")]
+mod otel;
+#[cfg(feature = "client")]
+mod properties;
+#[cfg(feature = "python")]
+mod python;
+mod rate_limit;
+#[cfg(feature = "client")]
+mod redirect;
+#[cfg(feature = "client")]
+mod resources;
+mod retry;
+mod scheme;
+mod session;
+mod signer;
+mod tenant;
+#[cfg(feature = "test-server")]
+mod test_server;
+#[cfg(feature = "test-utils")]
+mod test_utils;
+mod token;
+mod url_builder;
+#[cfg(feature = "vault")]
+mod vault;
+#[cfg(feature = "verify")]
+mod verify;
+#[cfg(all(feature = "wasm-bindings", target_arch = "wasm32"))]
+mod wasm;
+#[cfg(feature = "client")]
+mod webhooks;
+
+use serde::{Deserialize, Serialize};
+use std::fmt;
 use std::time;
 use url::Url;
 
+pub use api_error::ApiError;
+#[cfg(feature = "aws")]
+pub use aws::AwsSecretsManager;
+#[cfg(feature = "client")]
+pub use client::{BlockingTenantClient, TenantClient};
+pub use clock::{Clock, SystemClock};
+pub use credentials::Credentials;
+#[cfg(feature = "client")]
+pub use drift::ClockDriftCheck;
+pub use event::{AuthEvent, AuthEventKind, AuthEventSink};
+#[cfg(feature = "gcp")]
+pub use gcp::GcpSecretManager;
+#[cfg(feature = "global")]
+pub use global::{init_global, sign};
+#[cfg(feature = "client")]
+pub use graphql::GraphQlClient;
+#[cfg(feature = "client")]
+pub use health::{verify_credentials, CredentialsStatus};
+#[cfg(feature = "prometheus")]
+pub use metrics::AuthMetrics;
+#[cfg(feature = "otel")]
+pub use otel::OtelMetrics;
+#[cfg(feature = "client")]
+pub use properties::AppProperties;
+pub use rate_limit::RateLimiter;
+#[cfg(feature = "client")]
+pub use resources::{
+    oauth_authorize_url, AccessibleResource, AccessibleResources,
+};
+pub use retry::RetryPolicy;
+pub use scheme::{AuthScheme, AuthenticatedUrl};
+pub use session::{SessionClaims, SessionTokens};
+pub use signer::{SignedRequest, Signer};
+#[cfg(feature = "verify")]
+pub use tenant::verify_tenant_token;
+pub use tenant::{AsyncTenantStore, SyncTenantStore, TenantAuth, TenantStore};
+#[cfg(feature = "test-server")]
+pub use test_server::{MockAtlassianServer, ReceivedRequest};
+#[cfg(feature = "test-utils")]
+pub use test_utils::{
+    InMemoryTenantStore, MockClock, TestInstance, TokenFactory,
+};
+pub use token::ParsedToken;
+pub use url_builder::{encode_jql, UrlBuilder};
+#[cfg(feature = "vault")]
+pub use vault::{VaultClient, VaultKvVersion, VaultSecret, VaultTenantStore};
+#[cfg(feature = "verify")]
+pub use verify::{verify_token, ContextClaims, Verifier};
+#[cfg(feature = "client")]
+pub use webhooks::{RegistrationResult, Webhook, WebhookDetails, Webhooks};
+
 /// The set of characters to percent-encode for query parameters. The
 /// Jira documentation says these should be consistent with OAuth 1.0,
 /// which is defined in RFC 5849.
+pub use atlassian_app_auth_core::QUERY_PARAM_ENCODE_SET;
+
+/// Connect App shared secret.
 ///
-/// From <https://tools.ietf.org/html/rfc5849#page-29>:
-/// * (ALPHA, DIGIT, "-", ".", "_", "~") MUST NOT be encoded
-/// * All other characters MUST be encoded.
-pub const QUERY_PARAM_ENCODE_SET: &AsciiSet = &CONTROLS
-    .add(b' ')
-    .add(b'!')
-    .add(b'"')
-    .add(b'#')
-    .add(b'$')
-    .add(b'%')
-    .add(b'&')
-    .add(b'\'')
-    .add(b'(')
-    .add(b')')
-    .add(b'*')
-    .add(b'+')
-    .add(b',')
-    .add(b'/')
-    .add(b':')
-    .add(b';')
-    .add(b'<')
-    .add(b'=')
-    .add(b'>')
-    .add(b'?')
-    .add(b'@')
-    .add(b'[')
-    .add(b'\\')
-    .add(b']')
-    .add(b'^')
-    .add(b'`')
-    .add(b'{')
-    .add(b'|')
-    .add(b'}');
+/// The [`Debug`] and [`Display`] implementations print `[REDACTED]`
+/// instead of the secret, so it can't accidentally end up in a log
+/// message. Call [`SharedSecret::expose`] to get the actual value.
+///
+/// [`Display`]: fmt::Display
+#[derive(Clone, Deserialize)]
+#[serde(transparent)]
+pub struct SharedSecret(String);
+
+impl SharedSecret {
+    /// Create a new `SharedSecret`.
+    pub fn new<S: Into<String>>(secret: S) -> SharedSecret {
+        SharedSecret(secret.into())
+    }
+
+    /// Get the secret value. Take care not to log the result.
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+
+    /// Check that the secret looks like a real shared secret. This
+    /// catches obvious configuration mistakes such as an unset
+    /// environment variable or a trailing newline.
+    ///
+    /// Returns [`AuthError::SecretRejected`] if the secret is empty,
+    /// has leading or trailing whitespace, or is shorter than
+    /// [`SharedSecret::MIN_LEN`] bytes.
+    pub fn validate(&self) -> Result<(), AuthError> {
+        let reason = if self.0.is_empty() {
+            "secret is empty"
+        } else if self.0.trim() != self.0 {
+            "secret has leading or trailing whitespace"
+        } else if self.0.len() < SharedSecret::MIN_LEN {
+            "secret is too short"
+        } else {
+            return Ok(());
+        };
+        Err(AuthError::SecretRejected { reason })
+    }
+
+    /// Minimum length in bytes accepted by [`SharedSecret::validate`].
+    /// Shared secrets issued by Atlassian are much longer than this.
+    pub const MIN_LEN: usize = 32;
+}
+
+impl From<String> for SharedSecret {
+    fn from(secret: String) -> SharedSecret {
+        SharedSecret(secret)
+    }
+}
+
+impl From<&str> for SharedSecret {
+    fn from(secret: &str) -> SharedSecret {
+        SharedSecret(secret.into())
+    }
+}
+
+impl fmt::Debug for SharedSecret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("SharedSecret([REDACTED])")
+    }
+}
+
+impl fmt::Display for SharedSecret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("[REDACTED]")
+    }
+}
 
 /// Input parameters for creating a JWT.
+///
+/// When deserialized, `valid_for` is a number of seconds.
+#[derive(Debug, Deserialize)]
 pub struct Parameters {
     /// HTTP of the request.
     pub method: String,
@@ -70,6 +238,7 @@ pub struct Parameters {
 
     /// Duration that this key will be valid for (starting from the
     /// current time)
+    #[serde(deserialize_with = "credentials::deserialize_secs")]
     pub valid_for: time::Duration,
 
     /// Connect App key. This is the same as the "key" field
@@ -79,50 +248,232 @@ pub struct Parameters {
 
     /// Connect App shared secret. This is returned in the
     /// "sharedSecret" field of the installation lifecycle callback.
-    pub shared_secret: String,
+    pub shared_secret: SharedSecret,
+}
+
+impl Parameters {
+    /// Create a new `Parameters` for a request. The app key, shared
+    /// secret, and `valid_for` are copied from `credentials`.
+    pub fn new<M: Into<String>>(
+        method: M,
+        url: Url,
+        credentials: &Credentials,
+    ) -> Parameters {
+        Parameters {
+            method: method.into(),
+            url,
+            valid_for: credentials.valid_for,
+            app_key: credentials.app_key.clone(),
+            shared_secret: credentials.shared_secret.clone(),
+        }
+    }
+}
+
+/// Reason that a JWT failed verification.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum VerificationFailure {
+    /// The token could not be parsed.
+    Malformed,
+
+    /// The token's signature does not match the shared secret.
+    InvalidSignature,
+
+    /// The token is signed with an algorithm other than HS256.
+    UnsupportedAlgorithm,
+
+    /// The token has expired.
+    Expired,
+
+    /// The token is not valid yet.
+    NotYetValid,
+
+    /// The token was issued by someone else.
+    InvalidIssuer,
+}
+
+impl fmt::Display for VerificationFailure {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            VerificationFailure::Malformed => "malformed token",
+            VerificationFailure::InvalidSignature => "invalid signature",
+            VerificationFailure::UnsupportedAlgorithm => {
+                "unsupported algorithm"
+            }
+            VerificationFailure::Expired => "token expired",
+            VerificationFailure::NotYetValid => "token not valid yet",
+            VerificationFailure::InvalidIssuer => "invalid issuer",
+        })
+    }
 }
 
 /// Authentication error enum.
+///
+/// Errors never include the shared secret or a token, so they are
+/// safe to log.
 #[derive(thiserror::Error, Debug)]
+#[non_exhaustive]
 pub enum AuthError {
-    /// An error occurred when trying to encode the JWT.
-    #[error("JWT encoding failed: {0}")]
+    /// An error occurred when trying to encode or decode the JWT.
+    #[error("JWT error: {0}")]
     JwtError(#[from] jsonwebtoken::errors::Error),
 
+    /// The request URL can't be signed.
+    #[error("invalid URL: {reason}")]
+    InvalidUrl {
+        /// Why the URL is invalid.
+        reason: &'static str,
+    },
+
+    /// The request method is not a valid HTTP method.
+    #[error("invalid HTTP method: {method:?}")]
+    InvalidMethod {
+        /// The invalid method.
+        method: String,
+    },
+
+    /// The shared secret failed validation.
+    #[error("shared secret rejected: {reason}")]
+    SecretRejected {
+        /// Why the secret was rejected.
+        reason: &'static str,
+    },
+
+    /// The token's validity period is not valid, e.g. it expires
+    /// before it is issued.
+    #[error("invalid token lifetime: {reason}")]
+    InvalidLifetime {
+        /// Why the lifetime is invalid.
+        reason: &'static str,
+    },
+
+    /// The request's host is not in the signer's allow-list. See
+    /// [`Signer::with_allowed_hosts`].
+    #[error("host not allowed: {host}")]
+    HostNotAllowed {
+        /// Host of the request, or an empty string if the request has
+        /// no host.
+        host: String,
+    },
+
+    /// A JWT failed verification.
+    #[error("verification failed: {reason}")]
+    VerificationFailed {
+        /// Why verification failed.
+        reason: VerificationFailure,
+    },
+
+    /// The query string hash in a JWT does not match the request.
+    #[error("query string hash mismatch: expected {expected}, got {actual}")]
+    QshMismatch {
+        /// Query string hash computed from the request.
+        expected: String,
+        /// Query string hash in the JWT.
+        actual: String,
+        /// Canonical request that `expected` was computed from. This
+        /// is not included in the error message, since it contains
+        /// the request's query parameters, but it is useful for
+        /// working out which part of the request was canonicalized
+        /// differently by the sender.
+        canonical_request: String,
+    },
+
+    /// The tenant that issued a token is not in the [`TenantStore`].
+    #[error("unknown tenant")]
+    UnknownTenant,
+
+    /// A [`TenantStore`] or other credential source failed.
+    #[error("credential store error: {0}")]
+    StoreError(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    /// An HTTP request to an Atlassian API failed.
+    #[error("request error: {0}")]
+    RequestError(#[source] Box<dyn std::error::Error + Send + Sync>),
+
+    /// An Atlassian API responded with an error status.
+    #[error("API error: {0}")]
+    ApiError(#[from] ApiError),
+
     /// Something very unexpected happened with time itself.
     #[error("system time error: {0}")]
     TimeError(#[from] time::SystemTimeError),
 }
 
-// TODO: there are quite a few special cases described in the doc
-// linked above that are not yet handled here.
-fn create_canonical_request(params: &Parameters) -> String {
-    let url = &params.url;
-    let method = params.method.as_str().to_uppercase();
-    // Assume the path is already canonical
-    let path = url.path();
-
-    let mut query_pairs = url
-        .query_pairs()
-        .map(|(key, val)| {
-            format!(
-                "{}={}",
-                key,
-                utf8_percent_encode(&val, QUERY_PARAM_ENCODE_SET)
-            )
-        })
-        .collect::<Vec<_>>();
-    query_pairs.sort_unstable();
+fn store_error<E: Into<Box<dyn std::error::Error + Send + Sync>>>(
+    err: E,
+) -> AuthError {
+    AuthError::StoreError(err.into())
+}
 
-    format!("{}&{}&{}", method, path, query_pairs.join("&"))
+#[cfg(feature = "client")]
+fn request_error<E: Into<Box<dyn std::error::Error + Send + Sync>>>(
+    err: E,
+) -> AuthError {
+    AuthError::RequestError(err.into())
 }
 
-fn create_query_string_hash(params: &Parameters) -> String {
-    let canonical_request = create_canonical_request(params);
-    format!("{:x}", sha2::Sha256::digest(canonical_request.as_bytes()))
+fn create_canonical_request_from_parts<K, V>(
+    method: &str,
+    path: &str,
+    query: &[(K, V)],
+) -> String
+where
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    atlassian_app_auth_core::canonical_request(method, path, query)
 }
 
-#[derive(Debug, Serialize)]
+fn create_canonical_request(method: &str, url: &Url) -> String {
+    let query = url.query_pairs().collect::<Vec<_>>();
+    create_canonical_request_from_parts(method, url.path(), &query)
+}
+
+/// Check that `method` is a valid HTTP method token, as defined in
+/// RFC 7230.
+fn validate_method(method: &str) -> Result<(), AuthError> {
+    atlassian_app_auth_core::validate_method(method).map_err(|err| match err {
+        atlassian_app_auth_core::Error::InvalidMethod { method } => {
+            AuthError::InvalidMethod { method }
+        }
+        _ => AuthError::InvalidMethod {
+            method: method.into(),
+        },
+    })
+}
+
+fn hash_canonical_request(canonical_request: &str) -> String {
+    let qsh = atlassian_app_auth_core::query_string_hash(canonical_request);
+    instrument::qsh_computed(&qsh);
+    qsh
+}
+
+fn create_query_string_hash(method: &str, url: &Url) -> String {
+    hash_canonical_request(&create_canonical_request(method, url))
+}
+
+/// Create the query string hash ("qsh" claim) for a request that has
+/// already been split into components, for callers that don't have a
+/// [`Url`] (e.g. those using `http::Uri`).
+///
+/// `path` is the request path without the query string. `query` holds
+/// the query parameters as decoded (not percent-encoded) key/value
+/// pairs, in any order.
+pub fn create_query_string_hash_from_parts<K, V>(
+    method: &str,
+    path: &str,
+    query: &[(K, V)],
+) -> String
+where
+    K: AsRef<str>,
+    V: AsRef<str>,
+{
+    hash_canonical_request(&create_canonical_request_from_parts(
+        method, path, query,
+    ))
+}
+
+#[derive(Debug, Deserialize, Serialize)]
 struct Claims {
     /// The issuer of the claim. This matches the key in the app
     /// descriptor (e.g. "com.example.app").
@@ -136,27 +487,99 @@ struct Claims {
 
     /// JWT expiration time.
     exp: u64,
+
+    /// Time before which the JWT must not be accepted. This is only set
+    /// for tokens created ahead of time with
+    /// [`Signer::create_token_at`].
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    nbf: Option<u64>,
+
+    /// Atlassian account ID of the user, in tokens sent by Atlassian
+    /// on behalf of a user. Tokens created by this crate don't set it.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    sub: Option<String>,
 }
 
 impl Claims {
-    fn new(params: &Parameters) -> Result<Claims, AuthError> {
-        let now = time::SystemTime::now()
-            .duration_since(time::UNIX_EPOCH)?
-            .as_secs();
+    fn new(
+        qsh: String,
+        app_key: &str,
+        valid_for: time::Duration,
+        clock: &dyn Clock,
+    ) -> Result<Claims, AuthError> {
+        let now = clock::unix_time(clock)?;
         Ok(Claims {
-            iss: params.app_key.clone(),
-            qsh: create_query_string_hash(params),
+            iss: app_key.into(),
+            qsh,
 
             // The time that this JWT was issued (now)
             iat: now,
 
             // JWT expiration time
-            exp: now + params.valid_for.as_secs(),
+            exp: now + valid_for.as_secs(),
+
+            nbf: None,
+            sub: None,
+        })
+    }
+
+    /// Create claims for a token that becomes valid at `not_before`,
+    /// which can be in the future.
+    fn new_at(
+        qsh: String,
+        app_key: &str,
+        valid_for: time::Duration,
+        not_before: time::SystemTime,
+    ) -> Result<Claims, AuthError> {
+        let iat = not_before.duration_since(time::UNIX_EPOCH)?.as_secs();
+        let exp = iat + valid_for.as_secs();
+        if exp <= iat {
+            return Err(AuthError::InvalidLifetime {
+                reason: "token must expire after it is issued",
+            });
+        }
+        Ok(Claims {
+            iss: app_key.into(),
+            qsh,
+            iat,
+            exp,
+            nbf: Some(iat),
+            sub: None,
+        })
+    }
+
+    fn into_token(
+        self,
+        shared_secret: &SharedSecret,
+    ) -> Result<IssuedToken, AuthError> {
+        instrument::token_created(&self.iss, self.iat, self.exp);
+        let token = atlassian_app_auth_core::encode_token(
+            &atlassian_app_auth_core::Claims {
+                iss: &self.iss,
+                qsh: &self.qsh,
+                iat: self.iat,
+                exp: self.exp,
+                nbf: self.nbf,
+            },
+            shared_secret.expose().as_bytes(),
+        );
+
+        Ok(IssuedToken {
+            header: Header {
+                name: "Authorization",
+                value: format!("JWT {}", token),
+            },
+            token,
+            issued_at: time::UNIX_EPOCH + time::Duration::from_secs(self.iat),
+            expires_at: time::UNIX_EPOCH + time::Duration::from_secs(self.exp),
         })
     }
 }
 
 /// Request header.
+///
+/// The [`Debug`] implementation does not print the header value,
+/// since it contains a token that could be replayed.
 pub struct Header {
     /// Header name.
     pub name: &'static str,
@@ -164,22 +587,80 @@ pub struct Header {
     pub value: String,
 }
 
-/// Create an authentication [`Header`].
-pub fn create_auth_header(params: &Parameters) -> Result<Header, AuthError> {
-    let claims = Claims::new(params)?;
+impl fmt::Debug for Header {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Header")
+            .field("name", &self.name)
+            .field("value", &"[REDACTED]")
+            .finish()
+    }
+}
+
+/// Authentication [`Header`] along with companion headers, created by
+/// [`Signer::create_headers`].
+#[derive(Debug)]
+pub struct Headers {
+    /// Authentication header containing the token.
+    pub auth: Header,
+    /// Other headers to send with the request. See
+    /// [`Signer::with_companion_header`].
+    pub companions: Vec<Header>,
+}
+
+impl Headers {
+    /// Iterate over all the headers, starting with the authentication
+    /// header.
+    pub fn iter(&self) -> impl Iterator<Item = &Header> {
+        std::iter::once(&self.auth).chain(&self.companions)
+    }
+}
+
+/// Newly created token along with the times it is valid between.
+///
+/// Like [`Header`], the [`Debug`] implementation does not print the
+/// token.
+pub struct IssuedToken {
+    /// Authentication header containing the token.
+    pub header: Header,
+    /// The encoded JWT.
+    pub token: String,
+    /// Time the token was issued (the `iat` claim).
+    pub issued_at: time::SystemTime,
+    /// Time the token expires (the `exp` claim). A request that will
+    /// still be in flight after this time needs a new token. See also
+    /// [`IssuedToken::parsed`].
+    pub expires_at: time::SystemTime,
+}
 
-    let token = jsonwebtoken::encode(
-        &jsonwebtoken::Header::default(),
-        &claims,
-        &jsonwebtoken::EncodingKey::from_secret(
-            params.shared_secret.as_bytes(),
-        ),
-    )?;
+impl fmt::Debug for IssuedToken {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IssuedToken")
+            .field("header", &self.header)
+            .field("token", &"[REDACTED]")
+            .field("issued_at", &self.issued_at)
+            .field("expires_at", &self.expires_at)
+            .finish()
+    }
+}
 
-    Ok(Header {
+/// Create an authentication [`Header`] containing an OAuth 2.0 access
+/// token, for requests through the `api.atlassian.com` gateway. See
+/// [`UrlBuilder::gateway`].
+///
+/// Unlike JWTs, bearer tokens aren't tied to a particular request, so
+/// the same header can be reused until the access token expires.
+pub fn create_bearer_auth_header(access_token: &SharedSecret) -> Header {
+    Header {
         name: "Authorization",
-        value: format!("JWT {}", token),
-    })
+        value: format!("Bearer {}", access_token.expose()),
+    }
+}
+
+/// Create an authentication [`Header`].
+pub fn create_auth_header(params: &Parameters) -> Result<Header, AuthError> {
+    Signer::new(params.app_key.clone(), params.shared_secret.clone())
+        .with_valid_for(params.valid_for)
+        .create_auth_header(&params.method, &params.url)
 }
 
 #[cfg(test)]
@@ -191,7 +672,7 @@ mod tests {
             method: method.into(),
             url: Url::parse(url).unwrap(),
             app_key: String::new(),
-            shared_secret: String::new(),
+            shared_secret: SharedSecret::new(""),
             valid_for: time::Duration::new(0, 0),
         }
     }
@@ -203,7 +684,7 @@ mod tests {
             "https://somecorp.atlassian.net/rest/api/3/project/search?query=myproject",
         );
         assert_eq!(
-            create_canonical_request(&params),
+            create_canonical_request(&params.method, &params.url),
             "GET&/rest/api/3/project/search&query=myproject"
         );
     }
@@ -215,16 +696,227 @@ mod tests {
             "https://example.com/example?query=x y,z%2B*~",
         );
         assert_eq!(
-            create_canonical_request(&params),
+            create_canonical_request(&params.method, &params.url),
             "GET&/example&query=x%20y%2Cz%2B%2A~"
         );
     }
 
+    #[test]
+    fn test_shared_secret_redacted() {
+        let secret = SharedSecret::new("hunter2");
+        assert_eq!(format!("{}", secret), "[REDACTED]");
+        assert_eq!(format!("{:?}", secret), "SharedSecret([REDACTED])");
+        assert_eq!(secret.expose(), "hunter2");
+    }
+
+    #[test]
+    fn test_shared_secret_validate() {
+        let reason = |secret: &str| match SharedSecret::new(secret).validate() {
+            Err(AuthError::SecretRejected { reason }) => reason,
+            other => panic!("unexpected result: {:?}", other),
+        };
+        assert_eq!(reason(""), "secret is empty");
+        assert_eq!(
+            reason(&format!("{}\n", "a".repeat(40))),
+            "secret has leading or trailing whitespace"
+        );
+        assert_eq!(reason("short"), "secret is too short");
+
+        SharedSecret::new("a".repeat(SharedSecret::MIN_LEN))
+            .validate()
+            .unwrap();
+    }
+
+    #[test]
+    fn test_debug_redacted() {
+        let mut params = create_params("get", "https://example.com/example");
+        params.shared_secret = SharedSecret::new("hunter2");
+        params.valid_for = time::Duration::from_secs(60);
+        assert!(!format!("{:?}", params).contains("hunter2"));
+
+        let header = create_auth_header(&params).unwrap();
+        let token = header.value.strip_prefix("JWT ").unwrap();
+        let debug = format!("{:?}", header);
+        assert!(!debug.contains(token));
+        assert!(debug.contains("Authorization"));
+    }
+
+    #[test]
+    fn test_query_string_hash_from_parts() {
+        let params =
+            create_params("get", "https://example.com/example?b=x%20y&a=1&a=0");
+        assert_eq!(
+            create_query_string_hash_from_parts(
+                "GET",
+                "/example",
+                &[("a", "1"), ("b", "x y"), ("a", "0")],
+            ),
+            create_query_string_hash(&params.method, &params.url)
+        );
+        assert_eq!(
+            create_query_string_hash_from_parts::<&str, &str>(
+                "get",
+                "/example",
+                &[]
+            ),
+            "0073e2edb5df6a8af18c4398d32532f2b46a05295d10fac402131dd044032a61"
+        );
+    }
+
+    #[test]
+    fn test_validate_method() {
+        validate_method("GET").unwrap();
+        validate_method("get").unwrap();
+        for method in ["", "GET /", "G\nET"] {
+            assert!(matches!(
+                validate_method(method),
+                Err(AuthError::InvalidMethod { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_parameters_new() {
+        let creds = Credentials::new("app", SharedSecret::new("secret"));
+        let params = Parameters::new(
+            "GET",
+            Url::parse("https://example.com/example").unwrap(),
+            &creds,
+        );
+        assert_eq!(params.method, "GET");
+        assert_eq!(params.app_key, "app");
+        assert_eq!(params.valid_for, Signer::DEFAULT_VALID_FOR);
+        create_auth_header(&params).unwrap();
+    }
+
+    #[test]
+    fn test_deserialize_parameters() {
+        let params: Parameters = serde_json::from_str(
+            r#"{
+                "method": "GET",
+                "url": "https://example.com/example",
+                "valid_for": 30,
+                "app_key": "app",
+                "shared_secret": "secret"
+            }"#,
+        )
+        .unwrap();
+        assert_eq!(params.url.as_str(), "https://example.com/example");
+        assert_eq!(params.valid_for, time::Duration::from_secs(30));
+        assert_eq!(params.shared_secret.expose(), "secret");
+    }
+
+    /// Compare the canonical request against a straightforward
+    /// implementation of the encoding rules for pseudo-randomly
+    /// generated query parameters. See also the fuzz target in the
+    /// `fuzz` directory, which does the same for arbitrary URLs.
+    #[test]
+    fn test_canonical_request_differential() {
+        fn reference_encode(s: &str) -> String {
+            s.bytes()
+                .map(|b| {
+                    if b.is_ascii_alphanumeric() || b"-._~".contains(&b) {
+                        (b as char).to_string()
+                    } else {
+                        format!("%{:02X}", b)
+                    }
+                })
+                .collect()
+        }
+
+        let chars = [
+            'a',
+            'Z',
+            '0',
+            ' ',
+            '+',
+            '&',
+            '=',
+            '%',
+            '"',
+            '\'',
+            ',',
+            '/',
+            '~',
+            '*',
+            '\u{e9}',
+            '\u{1f600}',
+        ];
+        // xorshift, so that failures are reproducible.
+        let mut state = 0x2545_f491_4f6c_dd1d_u64;
+        let mut next = move |n: usize| {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            (state % n as u64) as usize
+        };
+
+        for _ in 0..500 {
+            let query = (0..next(4))
+                .map(|i| {
+                    let value = (0..next(8))
+                        .map(|_| chars[next(chars.len())])
+                        .collect();
+                    (format!("k{}", i), value)
+                })
+                .collect::<Vec<(String, String)>>();
+
+            let mut url = Url::parse("https://example.com/path").unwrap();
+            for (key, value) in &query {
+                url.query_pairs_mut().append_pair(key, value);
+            }
+
+            let mut expected = query
+                .iter()
+                .map(|(k, v)| format!("{}={}", k, reference_encode(v)))
+                .collect::<Vec<_>>();
+            expected.sort();
+            let expected = format!("GET&/path&{}", expected.join("&"));
+
+            assert_eq!(create_canonical_request("get", &url), expected);
+            assert_eq!(
+                create_canonical_request_from_parts("get", "/path", &query),
+                expected
+            );
+        }
+    }
+
+    /// Tokens are encoded by atlassian-app-auth-core; check that they
+    /// match what jsonwebtoken produces, with and without `nbf`.
+    #[test]
+    fn test_token_matches_jsonwebtoken() {
+        let secret = SharedSecret::new("secret");
+        for nbf in [None, Some(1_600_000_000)] {
+            let claims = Claims {
+                iss: "app".into(),
+                qsh: "a".repeat(64),
+                iat: 1_600_000_000,
+                exp: 1_600_000_180,
+                nbf,
+                sub: None,
+            };
+            let expected = jsonwebtoken::encode(
+                &jsonwebtoken::Header::default(),
+                &claims,
+                &jsonwebtoken::EncodingKey::from_secret(b"secret"),
+            )
+            .unwrap();
+            assert_eq!(claims.into_token(&secret).unwrap().token, expected);
+        }
+    }
+
+    #[test]
+    fn test_bearer_auth_header() {
+        let header = create_bearer_auth_header(&SharedSecret::new("token"));
+        assert_eq!(header.name, "Authorization");
+        assert_eq!(header.value, "Bearer token");
+    }
+
     #[test]
     fn test_query_string_hash() {
         let params = create_params("get", "https://example.com/example");
         assert_eq!(
-            create_query_string_hash(&params),
+            create_query_string_hash(&params.method, &params.url),
             "0073e2edb5df6a8af18c4398d32532f2b46a05295d10fac402131dd044032a61"
         );
     }
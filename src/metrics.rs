@@ -0,0 +1,131 @@
+use crate::event::{operation_label, outcome_label};
+use crate::{AuthEvent, AuthEventKind, AuthEventSink};
+use prometheus::{CounterVec, HistogramOpts, HistogramVec, Opts, Registry};
+
+/// [`AuthEventSink`] that records Prometheus metrics for tokens
+/// created and verified.
+///
+/// The metrics are registered with the [`Registry`] passed to
+/// [`AuthMetrics::new`], so they are exported along with the app's
+/// other metrics:
+/// * `atlassian_app_auth_operations_total` (counter), labeled with
+///   `operation` (`sign` or `verify`) and `outcome` (`ok` or a failure
+///   reason such as `expired` or `qsh_mismatch`).
+/// * `atlassian_app_auth_operation_duration_seconds` (histogram),
+///   labeled with `operation`. See [`AuthEvent::elapsed`].
+///
+/// These are the same instruments and labels as the `otel` feature's
+/// `OtelMetrics`.
+///
+/// ```
+/// use atlassian_app_auth::{AuthMetrics, SharedSecret, Signer};
+/// use std::sync::Arc;
+///
+/// let registry = prometheus::Registry::new();
+/// let metrics = AuthMetrics::new(&registry).unwrap();
+/// let signer = Signer::new("my-app", SharedSecret::new("secret"))
+///     .with_event_sink(Arc::new(metrics));
+/// ```
+#[derive(Clone, Debug)]
+pub struct AuthMetrics {
+    operations: CounterVec,
+    duration: HistogramVec,
+}
+
+impl AuthMetrics {
+    /// Create the metrics and register them with `registry`.
+    ///
+    /// Fails if metrics with the same names are already registered.
+    pub fn new(registry: &Registry) -> Result<AuthMetrics, prometheus::Error> {
+        let operations = CounterVec::new(
+            Opts::new(
+                "atlassian_app_auth_operations_total",
+                "Tokens created or verified.",
+            ),
+            &["operation", "outcome"],
+        )?;
+        let duration = HistogramVec::new(
+            HistogramOpts::new(
+                "atlassian_app_auth_operation_duration_seconds",
+                "Time spent creating or verifying tokens.",
+            ),
+            &["operation"],
+        )?;
+        registry.register(Box::new(operations.clone()))?;
+        registry.register(Box::new(duration.clone()))?;
+        Ok(AuthMetrics {
+            operations,
+            duration,
+        })
+    }
+
+    /// Number of operations of type `kind` with the given outcome
+    /// label (see the type documentation).
+    pub fn count(&self, kind: AuthEventKind, outcome: &str) -> u64 {
+        self.operations
+            .with_label_values(&[operation_label(kind), outcome])
+            .get() as u64
+    }
+}
+
+impl AuthEventSink for AuthMetrics {
+    fn record(&self, event: &AuthEvent) {
+        let operation = operation_label(event.kind);
+        self.operations
+            .with_label_values(&[operation, outcome_label(event.outcome)])
+            .inc();
+        self.duration
+            .with_label_values(&[operation])
+            .observe(event.elapsed.as_secs_f64());
+    }
+}
+
+#[cfg(all(test, feature = "verify"))]
+mod tests {
+    use super::*;
+    use crate::{SharedSecret, Signer, Verifier};
+    use prometheus::{Encoder, TextEncoder};
+    use std::sync::Arc;
+    use url::Url;
+
+    #[test]
+    fn test_metrics() {
+        let registry = Registry::new();
+        let metrics = Arc::new(AuthMetrics::new(&registry).unwrap());
+        let url = Url::parse("https://example.com/example").unwrap();
+        let signer = Signer::new("app", SharedSecret::new("secret"))
+            .with_event_sink(metrics.clone());
+        let verifier = Verifier::new(SharedSecret::new("secret"))
+            .with_event_sink(metrics.clone());
+
+        let header = signer.create_auth_header("GET", &url).unwrap();
+        let token = header.value.strip_prefix("JWT ").unwrap();
+        verifier.verify(token, "GET", &url).unwrap();
+        verifier.verify(token, "POST", &url).unwrap_err();
+        verifier.verify(token, "POST", &url).unwrap_err();
+
+        assert_eq!(metrics.count(AuthEventKind::Sign, "ok"), 1);
+        assert_eq!(metrics.count(AuthEventKind::Verify, "ok"), 1);
+        assert_eq!(metrics.count(AuthEventKind::Verify, "qsh_mismatch"), 2);
+
+        let mut rendered = Vec::new();
+        TextEncoder::new()
+            .encode(&registry.gather(), &mut rendered)
+            .unwrap();
+        let rendered = String::from_utf8(rendered).unwrap();
+        assert!(rendered.contains(
+            "atlassian_app_auth_operations_total\
+             {operation=\"verify\",outcome=\"qsh_mismatch\"} 2\n"
+        ));
+        assert!(rendered.contains(
+            "# TYPE atlassian_app_auth_operation_duration_seconds histogram\n"
+        ));
+        assert!(rendered.contains(
+            "atlassian_app_auth_operation_duration_seconds_count\
+             {operation=\"verify\"} 3\n"
+        ));
+
+        // The names are taken, so a second set can't be registered.
+        AuthMetrics::new(&registry).unwrap_err();
+    }
+}
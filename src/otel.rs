@@ -0,0 +1,142 @@
+use crate::event::{operation_label, outcome_label};
+use crate::{AuthEvent, AuthEventSink};
+use opentelemetry::metrics::{Counter, Histogram, Meter};
+use opentelemetry::KeyValue;
+
+/// [`AuthEventSink`] that records OpenTelemetry metrics for tokens
+/// created and verified.
+///
+/// The instruments are created with the [`Meter`] passed to
+/// [`OtelMetrics::new`], so they are exported by whatever
+/// `MeterProvider` the meter came from:
+/// * `atlassian_app_auth.operations` (counter), with the attributes
+///   `operation` (`sign` or `verify`) and `outcome` (`ok` or a failure
+///   reason such as `expired` or `qsh_mismatch`). These are the same
+///   labels as the `prometheus` feature's [`AuthMetrics`].
+/// * `atlassian_app_auth.operation.duration` (histogram, in seconds),
+///   with the attribute `operation`. See [`AuthEvent::elapsed`].
+///
+/// ```
+/// use atlassian_app_auth::{OtelMetrics, SharedSecret, Signer};
+/// use std::sync::Arc;
+///
+/// let meter = opentelemetry::global::meter("my-app");
+/// let signer = Signer::new("my-app", SharedSecret::new("secret"))
+///     .with_event_sink(Arc::new(OtelMetrics::new(&meter)));
+/// ```
+///
+/// [`AuthMetrics`]: crate::AuthMetrics
+#[derive(Debug)]
+pub struct OtelMetrics {
+    operations: Counter<u64>,
+    duration: Histogram<f64>,
+}
+
+impl OtelMetrics {
+    /// Create the instruments with `meter`.
+    pub fn new(meter: &Meter) -> OtelMetrics {
+        OtelMetrics {
+            operations: meter
+                .u64_counter("atlassian_app_auth.operations")
+                .with_description("Tokens created or verified.")
+                .build(),
+            duration: meter
+                .f64_histogram("atlassian_app_auth.operation.duration")
+                .with_description("Time spent creating or verifying tokens.")
+                .with_unit("s")
+                .build(),
+        }
+    }
+}
+
+impl AuthEventSink for OtelMetrics {
+    fn record(&self, event: &AuthEvent) {
+        let operation = KeyValue::new("operation", operation_label(event.kind));
+        self.operations.add(
+            1,
+            &[
+                operation.clone(),
+                KeyValue::new("outcome", outcome_label(event.outcome)),
+            ],
+        );
+        self.duration
+            .record(event.elapsed.as_secs_f64(), &[operation]);
+    }
+}
+
+#[cfg(all(test, feature = "verify"))]
+mod tests {
+    use super::*;
+    use crate::{SharedSecret, Signer, Verifier};
+    use opentelemetry::metrics::MeterProvider;
+    use opentelemetry_sdk::metrics::data::{AggregatedMetrics, MetricData};
+    use opentelemetry_sdk::metrics::{
+        InMemoryMetricExporter, PeriodicReader, SdkMeterProvider,
+    };
+    use std::sync::Arc;
+    use url::Url;
+
+    #[test]
+    fn test_otel_metrics() {
+        let exporter = InMemoryMetricExporter::default();
+        let provider = SdkMeterProvider::builder()
+            .with_reader(PeriodicReader::builder(exporter.clone()).build())
+            .build();
+        let metrics = Arc::new(OtelMetrics::new(&provider.meter("test")));
+
+        let url = Url::parse("https://example.com/example").unwrap();
+        let signer = Signer::new("app", SharedSecret::new("secret"))
+            .with_event_sink(metrics.clone());
+        let verifier = Verifier::new(SharedSecret::new("secret"))
+            .with_event_sink(metrics.clone());
+
+        let header = signer.create_auth_header("GET", &url).unwrap();
+        let token = header.value.strip_prefix("JWT ").unwrap();
+        verifier.verify(token, "GET", &url).unwrap();
+        verifier.verify(token, "POST", &url).unwrap_err();
+        verifier.verify(token, "POST", &url).unwrap_err();
+        provider.force_flush().unwrap();
+
+        let mut counts = Vec::new();
+        let mut durations = Vec::new();
+        let finished = exporter.get_finished_metrics().unwrap();
+        let resource_metrics = finished.last().unwrap();
+        for metric in resource_metrics
+            .scope_metrics()
+            .flat_map(|scope| scope.metrics())
+        {
+            match metric.data() {
+                AggregatedMetrics::U64(MetricData::Sum(sum)) => {
+                    assert_eq!(metric.name(), "atlassian_app_auth.operations");
+                    for point in sum.data_points() {
+                        let mut attributes: Vec<String> = point
+                            .attributes()
+                            .map(|kv| format!("{}={}", kv.key, kv.value))
+                            .collect();
+                        attributes.sort();
+                        counts.push((attributes.join(","), point.value()));
+                    }
+                }
+                AggregatedMetrics::F64(MetricData::Histogram(histogram)) => {
+                    assert_eq!(metric.unit(), "s");
+                    for point in histogram.data_points() {
+                        durations.push(point.count());
+                    }
+                }
+                other => panic!("unexpected metric: {:?}", other),
+            }
+        }
+        counts.sort();
+        durations.sort();
+
+        assert_eq!(
+            counts,
+            [
+                ("operation=sign,outcome=ok".to_string(), 1),
+                ("operation=verify,outcome=ok".to_string(), 1),
+                ("operation=verify,outcome=qsh_mismatch".to_string(), 2),
+            ]
+        );
+        assert_eq!(durations, [1, 3]);
+    }
+}
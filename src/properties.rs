@@ -0,0 +1,216 @@
+use crate::client::SiteClient;
+use crate::{
+    AuthError, ClockDriftCheck, RateLimiter, RetryPolicy, Signer, UrlBuilder,
+};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use url::Url;
+
+#[derive(Deserialize)]
+struct Property<T> {
+    value: T,
+}
+
+#[derive(Deserialize)]
+struct Keys {
+    keys: Vec<Key>,
+}
+
+#[derive(Deserialize)]
+struct Key {
+    key: String,
+}
+
+/// Whether `err` is for a response with status 404.
+fn is_not_found(err: &AuthError) -> bool {
+    matches!(err, AuthError::ApiError(err) if err.status == 404)
+}
+
+/// Stores per-tenant data with the Connect app properties REST API.
+///
+/// Properties are JSON values stored by the Atlassian site, keyed by
+/// the app key and a property key of up to 127 characters. The
+/// property key is part of the request path, so it is
+/// percent-encoded before the request is signed, and the token's query
+/// string hash matches the URL that is sent.
+///
+/// For Confluence, include `/wiki` in `base` and give the signer the
+/// same context path with [`Signer::with_context_path`].
+///
+/// This uses a blocking HTTP client, so it should not be called
+/// directly from async code.
+///
+/// ```no_run
+/// use atlassian_app_auth::{AppProperties, SharedSecret, Signer};
+/// use serde::{Deserialize, Serialize};
+/// use url::Url;
+///
+/// #[derive(Deserialize, Serialize)]
+/// struct Settings {
+///     enabled: bool,
+/// }
+///
+/// let signer = Signer::new("my-app", SharedSecret::new("secret"));
+/// let base = Url::parse("https://example.atlassian.net").unwrap();
+/// let properties = AppProperties::new(&base, signer);
+/// properties.set("settings", &Settings { enabled: true }).unwrap();
+/// let settings: Option<Settings> = properties.get("settings").unwrap();
+/// ```
+#[derive(Debug)]
+pub struct AppProperties {
+    site: SiteClient,
+}
+
+impl AppProperties {
+    /// Create a new `AppProperties` for the site at `base`, storing
+    /// properties of the app identified by the signer's app key.
+    pub fn new(base: &Url, signer: Signer) -> AppProperties {
+        AppProperties {
+            site: SiteClient::new(base, signer),
+        }
+    }
+
+    /// Retry failed requests according to `retry_policy` instead of
+    /// [`RetryPolicy::default`].
+    pub fn with_retry_policy(
+        mut self,
+        retry_policy: RetryPolicy,
+    ) -> AppProperties {
+        self.site.options.retry_policy = retry_policy;
+        self
+    }
+
+    /// Take a token from `rate_limiter` before each request. The
+    /// site's base URL is used as the tenant.
+    pub fn with_rate_limiter(
+        mut self,
+        rate_limiter: Arc<RateLimiter>,
+    ) -> AppProperties {
+        self.site.options.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Check the `Date` header of every response for clock drift with
+    /// `check`.
+    pub fn with_clock_drift_check(
+        mut self,
+        check: Arc<ClockDriftCheck>,
+    ) -> AppProperties {
+        self.site.options.clock_drift = Some(check);
+        self
+    }
+
+    /// Get the keys of all the app's properties.
+    pub fn keys(&self) -> Result<Vec<String>, AuthError> {
+        let url = self.url().build()?;
+        let keys: Keys = self.site.send::<(), _>("GET", url, None)?;
+        Ok(keys.keys.into_iter().map(|key| key.key).collect())
+    }
+
+    /// Get the value of the property `key`, or `None` if it is not
+    /// set.
+    pub fn get<T: DeserializeOwned>(
+        &self,
+        key: &str,
+    ) -> Result<Option<T>, AuthError> {
+        let url = self.url().segment(key).build()?;
+        match self.site.send::<(), Property<T>>("GET", url, None) {
+            Ok(property) => Ok(Some(property.value)),
+            Err(err) if is_not_found(&err) => Ok(None),
+            Err(err) => Err(err),
+        }
+    }
+
+    /// Set the property `key` to `value`.
+    pub fn set<T: Serialize + ?Sized>(
+        &self,
+        key: &str,
+        value: &T,
+    ) -> Result<(), AuthError> {
+        let url = self.url().segment(key).build()?;
+        self.site.execute("PUT", url, Some(value))?;
+        Ok(())
+    }
+
+    /// Delete the property `key`. Deleting a property that is not set
+    /// is not an error.
+    pub fn delete(&self, key: &str) -> Result<(), AuthError> {
+        let url = self.url().segment(key).build()?;
+        match self.site.execute::<()>("DELETE", url, None) {
+            Err(err) if !is_not_found(&err) => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    fn url(&self) -> UrlBuilder {
+        UrlBuilder::new(self.site.base())
+            .api("rest/atlassian-connect/1")
+            .path("addons")
+            .segment(self.site.signer().app_key())
+            .path("properties")
+    }
+}
+
+#[cfg(all(test, feature = "verify"))]
+mod tests {
+    use super::*;
+    use crate::fake_server::serve;
+    use crate::{SharedSecret, Verifier};
+
+    #[test]
+    fn test_app_properties() {
+        let server = serve(vec![
+            (200, r#"{"keys": [{"key": "a b", "self": "x"}]}"#.into()),
+            (
+                200,
+                r#"{"key": "a b", "value": {"n": 1}, "self": "x"}"#.into(),
+            ),
+            (404, r#"{"message": "not found"}"#.into()),
+            (200, String::new()),
+            (204, String::new()),
+            (404, String::new()),
+        ]);
+        let signer = Signer::new("app", SharedSecret::new("secret"));
+        let properties = AppProperties::new(&server.url, signer);
+
+        assert_eq!(properties.keys().unwrap(), ["a b"]);
+        assert_eq!(
+            properties.get::<serde_json::Value>("a b").unwrap(),
+            Some(serde_json::json!({"n": 1}))
+        );
+        assert_eq!(properties.get::<serde_json::Value>("c").unwrap(), None);
+        properties.set("a b", &serde_json::json!({"n": 2})).unwrap();
+        properties.delete("a b").unwrap();
+        properties.delete("c").unwrap();
+
+        let requests = server.requests.lock().unwrap();
+        let lines = requests
+            .iter()
+            .map(|r| r.head.lines().next().unwrap())
+            .collect::<Vec<_>>();
+        let path = "/rest/atlassian-connect/1/addons/app/properties";
+        assert_eq!(
+            lines,
+            [
+                format!("GET {} HTTP/1.1", path),
+                format!("GET {}/a%20b HTTP/1.1", path),
+                format!("GET {}/c HTTP/1.1", path),
+                format!("PUT {}/a%20b HTTP/1.1", path),
+                format!("DELETE {}/a%20b HTTP/1.1", path),
+                format!("DELETE {}/c HTTP/1.1", path),
+            ]
+        );
+        assert_eq!(requests[3].body, r#"{"n":2}"#);
+
+        // The token is valid for the encoded path that was sent.
+        let verifier = Verifier::new(SharedSecret::new("secret"));
+        let token = requests[3]
+            .head
+            .lines()
+            .find_map(|line| line.strip_prefix("authorization: JWT "))
+            .unwrap();
+        let url = server.url.join(&format!("{}/a%20b", path)).unwrap();
+        verifier.verify(token, "PUT", &url).unwrap();
+    }
+}
@@ -0,0 +1,190 @@
+//! Python bindings, for scripts that need to create or verify tokens
+//! with exactly the same canonical request and query string hash code
+//! as Rust services.
+//!
+//! Build and install the module into the current virtualenv with
+//! [maturin]:
+//!
+//! ```text
+//! maturin develop --release -m python/pyproject.toml
+//! ```
+//!
+//! Example:
+//!
+//! ```python
+//! import atlassian_app_auth as aaa
+//!
+//! header = aaa.create_auth_header(
+//!     "GET", "https://example.atlassian.net/rest/api/3/myself",
+//!     app_key="my-app", shared_secret=secret)
+//! aaa.verify_token(token, "GET", url, shared_secret=secret)
+//! ```
+//!
+//! [maturin]: https://www.maturin.rs
+
+use crate::{AuthError, SharedSecret, Signer, Verifier};
+use pyo3::create_exception;
+use pyo3::exceptions::PyException;
+use pyo3::prelude::*;
+use std::time::Duration;
+use url::Url;
+
+create_exception!(
+    atlassian_app_auth,
+    PyAuthError,
+    PyException,
+    "An operation failed."
+);
+create_exception!(
+    atlassian_app_auth,
+    InvalidUrlError,
+    PyAuthError,
+    "The URL or method could not be parsed or signed."
+);
+create_exception!(
+    atlassian_app_auth,
+    VerificationError,
+    PyAuthError,
+    "The token's signature, expiration time, or format is invalid."
+);
+create_exception!(
+    atlassian_app_auth,
+    QshMismatchError,
+    VerificationError,
+    "The token's query string hash does not match the request."
+);
+
+/// Convert an [`AuthError`] to the matching Python exception.
+fn to_py_err(err: AuthError) -> PyErr {
+    let message = err.to_string();
+    match err {
+        AuthError::InvalidUrl { .. } | AuthError::InvalidMethod { .. } => {
+            InvalidUrlError::new_err(message)
+        }
+        AuthError::VerificationFailed { .. } => {
+            VerificationError::new_err(message)
+        }
+        AuthError::QshMismatch { .. } => QshMismatchError::new_err(message),
+        _ => PyAuthError::new_err(message),
+    }
+}
+
+fn parse_url(url: &str) -> PyResult<Url> {
+    Url::parse(url).map_err(|err| {
+        InvalidUrlError::new_err(format!("invalid URL: {}", err))
+    })
+}
+
+/// Create the value of the Authorization header for a request, e.g.
+/// "JWT eyJ0eXAi...".
+#[pyfunction]
+#[pyo3(signature = (method, url, *, app_key, shared_secret, valid_for_secs = 180))]
+fn create_auth_header(
+    method: &str,
+    url: &str,
+    app_key: &str,
+    shared_secret: &str,
+    valid_for_secs: u64,
+) -> PyResult<String> {
+    let url = parse_url(url)?;
+    Signer::new(app_key, SharedSecret::new(shared_secret))
+        .with_valid_for(Duration::from_secs(valid_for_secs))
+        .create_auth_header(method, &url)
+        .map(|header| header.value)
+        .map_err(to_py_err)
+}
+
+/// Create the query string hash ("qsh" claim) for a request.
+#[pyfunction]
+fn create_query_string_hash(method: &str, url: &str) -> PyResult<String> {
+    Ok(crate::create_query_string_hash(method, &parse_url(url)?))
+}
+
+/// Verify a JWT sent by Atlassian, without the "JWT " prefix.
+///
+/// Raises VerificationError (or QshMismatchError) if the token is not
+/// valid for the request.
+#[pyfunction]
+#[pyo3(signature = (token, method, url, *, shared_secret))]
+fn verify_token(
+    token: &str,
+    method: &str,
+    url: &str,
+    shared_secret: &str,
+) -> PyResult<()> {
+    let url = parse_url(url)?;
+    Verifier::new(SharedSecret::new(shared_secret))
+        .verify(token, method, &url)
+        .map_err(to_py_err)
+}
+
+/// Create and verify Atlassian Connect JWTs.
+#[pymodule]
+fn atlassian_app_auth(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    let py = module.py();
+    module.add("AuthError", py.get_type::<PyAuthError>())?;
+    module.add("InvalidUrlError", py.get_type::<InvalidUrlError>())?;
+    module.add("VerificationError", py.get_type::<VerificationError>())?;
+    module.add("QshMismatchError", py.get_type::<QshMismatchError>())?;
+    module.add_function(wrap_pyfunction!(create_auth_header, module)?)?;
+    module.add_function(wrap_pyfunction!(create_query_string_hash, module)?)?;
+    module.add_function(wrap_pyfunction!(verify_token, module)?)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::PyDict;
+
+    #[test]
+    fn test_python_module() {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            let module = PyModule::new(py, "atlassian_app_auth").unwrap();
+            atlassian_app_auth(&module).unwrap();
+            let globals = PyDict::new(py);
+            globals.set_item("aaa", module).unwrap();
+
+            py.run(
+                cr#"
+url = "https://example.com/rest/api/3/myself?a=1"
+header = aaa.create_auth_header(
+    "GET", url, app_key="app", shared_secret="secret")
+assert header.startswith("JWT ")
+token = header[len("JWT "):]
+aaa.verify_token(token, "GET", url, shared_secret="secret")
+
+assert aaa.create_query_string_hash("GET", url) == (
+    "ec218e1238a4fbc6f10704ccf87cb5d25e694cb734db79f571f4da8c313a26e0")
+
+try:
+    aaa.verify_token(token, "POST", url, shared_secret="secret")
+    raise AssertionError("expected QshMismatchError")
+except aaa.QshMismatchError as err:
+    assert isinstance(err, aaa.VerificationError)
+    assert isinstance(err, aaa.AuthError)
+
+try:
+    aaa.verify_token(token, "GET", url, shared_secret="wrong")
+    raise AssertionError("expected VerificationError")
+except aaa.QshMismatchError:
+    raise AssertionError("wrong exception")
+except aaa.VerificationError:
+    pass
+
+for method, url in [("GET /", url), ("GET", "not a url")]:
+    try:
+        aaa.create_auth_header(
+            method, url, app_key="app", shared_secret="secret")
+        raise AssertionError("expected InvalidUrlError")
+    except aaa.InvalidUrlError:
+        pass
+"#,
+                Some(&globals),
+                None,
+            )
+            .unwrap();
+        });
+    }
+}
@@ -0,0 +1,171 @@
+use crate::{Clock, SystemClock};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, SystemTime};
+
+struct Bucket {
+    tokens: f64,
+    updated: SystemTime,
+}
+
+/// Token-bucket rate limiter with a separate bucket for each tenant.
+///
+/// Each bucket holds up to `burst` tokens and refills at
+/// `requests_per_second`. Taking a token before every request keeps a
+/// bulk job under Atlassian's rate limits rather than waiting for 429
+/// responses. Limiters are usually shared through an `Arc`, so that
+/// every client for a tenant draws from the same bucket.
+///
+/// ```
+/// use atlassian_app_auth::RateLimiter;
+///
+/// let limiter = RateLimiter::new(10.0).with_burst(2);
+/// assert!(limiter.try_acquire("tenant").is_ok());
+/// assert!(limiter.try_acquire("tenant").is_ok());
+/// assert!(limiter.try_acquire("tenant").is_err());
+/// assert!(limiter.try_acquire("other-tenant").is_ok());
+/// ```
+pub struct RateLimiter {
+    requests_per_second: f64,
+    burst: f64,
+    clock: Arc<dyn Clock>,
+    buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+    /// Create a new `RateLimiter` that allows `requests_per_second`
+    /// requests per tenant, with a burst of one second's worth of
+    /// requests (at least one).
+    ///
+    /// # Panics
+    ///
+    /// Panics if `requests_per_second` is not positive.
+    pub fn new(requests_per_second: f64) -> RateLimiter {
+        assert!(
+            requests_per_second > 0.0,
+            "requests_per_second must be positive"
+        );
+        RateLimiter {
+            requests_per_second,
+            burst: requests_per_second.max(1.0),
+            clock: Arc::new(SystemClock),
+            buckets: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Set the number of requests that can be made at once after a
+    /// tenant has been idle.
+    pub fn with_burst(mut self, burst: u32) -> RateLimiter {
+        self.burst = f64::from(burst.max(1));
+        self
+    }
+
+    /// Use `clock` to get the current time instead of [`SystemClock`].
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> RateLimiter {
+        self.clock = clock;
+        self
+    }
+
+    /// Take a token for `tenant` if one is available. Otherwise return
+    /// how long until one will be.
+    pub fn try_acquire(&self, tenant: &str) -> Result<(), Duration> {
+        let now = self.clock.now();
+        let mut buckets = self.buckets.lock().unwrap();
+        let bucket = buckets.entry(tenant.into()).or_insert(Bucket {
+            tokens: self.burst,
+            updated: now,
+        });
+
+        // If the clock went backwards, no time has elapsed.
+        let elapsed = now.duration_since(bucket.updated).unwrap_or_default();
+        bucket.tokens = (bucket.tokens
+            + elapsed.as_secs_f64() * self.requests_per_second)
+            .min(self.burst);
+        bucket.updated = now;
+
+        if bucket.tokens >= 1.0 {
+            bucket.tokens -= 1.0;
+            Ok(())
+        } else {
+            Err(Duration::from_secs_f64(
+                (1.0 - bucket.tokens) / self.requests_per_second,
+            ))
+        }
+    }
+
+    /// Take a token for `tenant`, sleeping until one is available.
+    pub fn acquire(&self, tenant: &str) {
+        while let Err(wait) = self.try_acquire(tenant) {
+            thread::sleep(wait);
+        }
+    }
+}
+
+impl fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("RateLimiter")
+            .field("requests_per_second", &self.requests_per_second)
+            .field("burst", &self.burst)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn test_rate_limiter() {
+        let now = Arc::new(Mutex::new(UNIX_EPOCH));
+        let clock_now = now.clone();
+        let limiter = RateLimiter::new(2.0)
+            .with_clock(Arc::new(move || *clock_now.lock().unwrap()));
+        let advance = |millis| {
+            *now.lock().unwrap() += Duration::from_millis(millis);
+        };
+
+        assert_eq!(limiter.try_acquire("a"), Ok(()));
+        assert_eq!(limiter.try_acquire("a"), Ok(()));
+        assert_eq!(limiter.try_acquire("a"), Err(Duration::from_millis(500)));
+
+        // Buckets are per tenant.
+        assert_eq!(limiter.try_acquire("b"), Ok(()));
+
+        advance(250);
+        assert_eq!(limiter.try_acquire("a"), Err(Duration::from_millis(250)));
+        advance(250);
+        assert_eq!(limiter.try_acquire("a"), Ok(()));
+
+        // Idle time doesn't build up more than the burst.
+        advance(10_000);
+        assert_eq!(limiter.try_acquire("a"), Ok(()));
+        assert_eq!(limiter.try_acquire("a"), Ok(()));
+        assert!(limiter.try_acquire("a").is_err());
+
+        // Going back in time doesn't add or remove tokens.
+        *now.lock().unwrap() -= Duration::from_secs(60);
+        assert_eq!(limiter.try_acquire("a"), Err(Duration::from_millis(500)));
+        advance(500);
+        assert_eq!(limiter.try_acquire("a"), Ok(()));
+    }
+
+    #[test]
+    fn test_clock_read_once() {
+        // Clock that advances by half a second every time it's read, so
+        // reading it more than once per call would add extra tokens.
+        let reads = Mutex::new(0);
+        let limiter = RateLimiter::new(1.0).with_burst(1).with_clock(Arc::new(
+            move || {
+                let mut reads = reads.lock().unwrap();
+                *reads += 1;
+                UNIX_EPOCH + Duration::from_millis(500 * *reads)
+            },
+        ));
+
+        assert_eq!(limiter.try_acquire("a"), Ok(()));
+        assert_eq!(limiter.try_acquire("a"), Err(Duration::from_millis(500)));
+    }
+}
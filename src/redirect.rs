@@ -0,0 +1,131 @@
+use crate::{request_error, AuthError};
+use reqwest::header::{HeaderMap, LOCATION};
+use reqwest::{Method, StatusCode};
+use url::Url;
+
+/// Maximum number of redirects to follow for one request.
+pub(crate) const MAX_REDIRECTS: usize = 10;
+
+/// Request to send, which changes as redirects are followed.
+///
+/// The HTTP clients are created with redirects disabled, since a
+/// token is only valid for the path and query it was created for.
+/// Instead, the caller sends the request, passes the response to
+/// [`Target::follow`], and if it was a redirect, signs and sends the
+/// updated request.
+pub(crate) struct Target {
+    pub(crate) method: Method,
+    pub(crate) url: Url,
+    pub(crate) body: Option<Vec<u8>>,
+    redirects: usize,
+}
+
+impl Target {
+    pub(crate) fn new(
+        method: Method,
+        url: Url,
+        body: Option<Vec<u8>>,
+    ) -> Target {
+        Target {
+            method,
+            url,
+            body,
+            redirects: 0,
+        }
+    }
+
+    /// Update the request for a response with `status` and `headers`.
+    /// Returns whether the response is a redirect that should be
+    /// followed.
+    ///
+    /// Redirects to a different origin are not followed, since that
+    /// would send a token for the tenant to another host.
+    pub(crate) fn follow(
+        &mut self,
+        status: StatusCode,
+        headers: &HeaderMap,
+    ) -> Result<bool, AuthError> {
+        if !status.is_redirection() || status == StatusCode::NOT_MODIFIED {
+            return Ok(false);
+        }
+        let location = match headers.get(LOCATION) {
+            Some(location) => location.to_str().map_err(request_error)?,
+            None => return Ok(false),
+        };
+        let url =
+            self.url.join(location).map_err(|_| AuthError::InvalidUrl {
+                reason: "invalid redirect location",
+            })?;
+        if url.origin() != self.url.origin() {
+            return Err(AuthError::InvalidUrl {
+                reason: "redirect to a different origin",
+            });
+        }
+        self.redirects += 1;
+        if self.redirects > MAX_REDIRECTS {
+            return Err(request_error("too many redirects"));
+        }
+
+        // Same as browsers: 303 always switches to GET, and 301 and
+        // 302 do for POST.
+        let to_get = status == StatusCode::SEE_OTHER
+            || (self.method == Method::POST
+                && (status == StatusCode::MOVED_PERMANENTLY
+                    || status == StatusCode::FOUND));
+        if to_get {
+            self.method = Method::GET;
+            self.body = None;
+        }
+        self.url = url;
+        Ok(true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
+
+    fn location(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(LOCATION, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_follow() {
+        let url = Url::parse("https://example.com/a?x=1").unwrap();
+        let mut target = Target::new(Method::POST, url, Some(b"{}".to_vec()));
+
+        assert!(!target.follow(StatusCode::OK, &location("/b")).unwrap());
+        assert!(!target.follow(StatusCode::FOUND, &HeaderMap::new()).unwrap());
+
+        assert!(target
+            .follow(StatusCode::TEMPORARY_REDIRECT, &location("/b?y=2"))
+            .unwrap());
+        assert_eq!(target.method, Method::POST);
+        assert_eq!(target.url.as_str(), "https://example.com/b?y=2");
+        assert!(target.body.is_some());
+
+        assert!(target.follow(StatusCode::FOUND, &location("c")).unwrap());
+        assert_eq!(target.method, Method::GET);
+        assert_eq!(target.url.as_str(), "https://example.com/c");
+        assert!(target.body.is_none());
+
+        assert!(matches!(
+            target.follow(
+                StatusCode::FOUND,
+                &location("https://other.example.com/c")
+            ),
+            Err(AuthError::InvalidUrl { .. })
+        ));
+
+        for _ in 2..MAX_REDIRECTS {
+            assert!(target.follow(StatusCode::FOUND, &location("c")).unwrap());
+        }
+        assert!(matches!(
+            target.follow(StatusCode::FOUND, &location("c")),
+            Err(AuthError::RequestError(_))
+        ));
+    }
+}
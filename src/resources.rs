@@ -0,0 +1,266 @@
+use crate::url_builder::GATEWAY_URL;
+use crate::{request_error, ApiError, AuthError, SharedSecret, UrlBuilder};
+use serde::Deserialize;
+use sha2::Digest;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// Atlassian's OAuth 2.0 authorization server.
+const AUTH_URL: &str = "https://auth.atlassian.com";
+
+/// Atlassian site that an OAuth 2.0 access token can be used with.
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct AccessibleResource {
+    /// Cloud ID of the site, used in gateway URLs. See
+    /// [`UrlBuilder::gateway`].
+    ///
+    /// [`UrlBuilder::gateway`]: crate::UrlBuilder::gateway
+    pub id: String,
+
+    /// Site URL, e.g. `https://example.atlassian.net`.
+    pub url: String,
+
+    /// Site name.
+    pub name: String,
+
+    /// Scopes granted for the site.
+    pub scopes: Vec<String>,
+
+    /// URL of the site's avatar.
+    #[serde(default)]
+    pub avatar_url: Option<String>,
+}
+
+impl AccessibleResource {
+    /// Whether `scope` has been granted for the site.
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|granted| granted == scope)
+    }
+}
+
+/// Create the URL that a user visits to grant an OAuth 2.0 (3LO) app
+/// access to their sites.
+///
+/// The audience is always `api.atlassian.com`, since that is the only
+/// audience Atlassian issues tokens for; tokens for it work with both
+/// the REST gateway ([`UrlBuilder::gateway`]) and the GraphQL gateway
+/// ([`GraphQlClient`]). `scopes` are separated with spaces, and should
+/// include `offline_access` if a refresh token is needed.
+///
+/// ```
+/// use atlassian_app_auth::oauth_authorize_url;
+/// use url::Url;
+///
+/// let redirect = Url::parse("https://example.com/callback").unwrap();
+/// let url = oauth_authorize_url(
+///     "client-id",
+///     &["read:jira-work", "read:me"],
+///     &redirect,
+///     "state",
+/// )
+/// .unwrap();
+/// assert!(url.as_str().contains("scope=read%3Ajira-work%20read%3Ame"));
+/// ```
+///
+/// [`GraphQlClient`]: crate::GraphQlClient
+pub fn oauth_authorize_url(
+    client_id: &str,
+    scopes: &[&str],
+    redirect_uri: &Url,
+    state: &str,
+) -> Result<Url, AuthError> {
+    let base =
+        Url::parse(AUTH_URL).expect("authorization server URL is not valid");
+    UrlBuilder::new(&base)
+        .path("authorize")
+        .query("audience", "api.atlassian.com")
+        .query("client_id", client_id)
+        .query("scope", &scopes.join(" "))
+        .query("redirect_uri", redirect_uri.as_str())
+        .query("state", state)
+        .query("response_type", "code")
+        .query("prompt", "consent")
+        .build()
+}
+
+type Cache = HashMap<Vec<u8>, (Vec<AccessibleResource>, Instant)>;
+
+/// Looks up the sites that an OAuth 2.0 access token grants access to,
+/// using the `oauth/token/accessible-resources` endpoint.
+///
+/// Results are cached per access token. This uses a blocking HTTP
+/// client, so it should not be called directly from async code.
+pub struct AccessibleResources {
+    http: reqwest::blocking::Client,
+    endpoint: Url,
+    ttl: Duration,
+    cache: Mutex<Cache>,
+}
+
+impl AccessibleResources {
+    /// Default time to cache the resources for an access token.
+    pub const DEFAULT_TTL: Duration = Duration::from_secs(300);
+
+    /// Create a new `AccessibleResources` client.
+    pub fn new() -> AccessibleResources {
+        let endpoint = Url::parse(GATEWAY_URL)
+            .and_then(|url| url.join("oauth/token/accessible-resources"))
+            .expect("accessible resources URL is not valid");
+        AccessibleResources {
+            http: reqwest::blocking::Client::new(),
+            endpoint,
+            ttl: AccessibleResources::DEFAULT_TTL,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Override the endpoint URL.
+    pub fn with_endpoint(mut self, endpoint: Url) -> AccessibleResources {
+        self.endpoint = endpoint;
+        self
+    }
+
+    /// Set how long to cache the resources for an access token.
+    pub fn with_ttl(mut self, ttl: Duration) -> AccessibleResources {
+        self.ttl = ttl;
+        self
+    }
+
+    /// Get the sites that `access_token` grants access to.
+    pub fn list(
+        &self,
+        access_token: &SharedSecret,
+    ) -> Result<Vec<AccessibleResource>, AuthError> {
+        // Key the cache by a hash of the token rather than the token
+        // itself.
+        let key = sha2::Sha256::digest(access_token.expose().as_bytes());
+        let key = key.to_vec();
+        let now = Instant::now();
+        if let Some((resources, expires)) = self.cache.lock().unwrap().get(&key)
+        {
+            if now < *expires {
+                return Ok(resources.clone());
+            }
+        }
+
+        let resp = self
+            .http
+            .get(self.endpoint.clone())
+            .bearer_auth(access_token.expose())
+            .header("Accept", "application/json")
+            .send()
+            .map_err(request_error)?;
+        let status = resp.status();
+        if status.is_client_error() || status.is_server_error() {
+            let body = resp.text().unwrap_or_default();
+            return Err(ApiError::parse(status.as_u16(), &body).into());
+        }
+        let resources: Vec<AccessibleResource> =
+            resp.json().map_err(request_error)?;
+
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(key, (resources.clone(), now + self.ttl));
+        Ok(resources)
+    }
+
+    /// Get the cloud ID of the site at `site_url` (e.g.
+    /// `https://example.atlassian.net`), or `None` if `access_token`
+    /// doesn't grant access to it.
+    pub fn cloud_id(
+        &self,
+        access_token: &SharedSecret,
+        site_url: &Url,
+    ) -> Result<Option<String>, AuthError> {
+        let site = site_url.as_str().trim_end_matches('/');
+        Ok(self
+            .list(access_token)?
+            .into_iter()
+            .find(|resource| resource.url.trim_end_matches('/') == site)
+            .map(|resource| resource.id))
+    }
+}
+
+impl Default for AccessibleResources {
+    fn default() -> AccessibleResources {
+        AccessibleResources::new()
+    }
+}
+
+impl fmt::Debug for AccessibleResources {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("AccessibleResources")
+            .field("endpoint", &self.endpoint)
+            .field("ttl", &self.ttl)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_server::serve;
+
+    #[test]
+    fn test_accessible_resources() {
+        let server = serve(vec![(
+            200,
+            serde_json::json!([{
+                "id": "1324a887-45db-1bf4-1e99-ef0ff456d421",
+                "name": "Site name",
+                "url": "https://example.atlassian.net",
+                "scopes": ["read:jira-work"],
+                "avatarUrl": "https://example.com/avatar.png",
+            }])
+            .to_string(),
+        )]);
+        let client = AccessibleResources::new()
+            .with_endpoint(server.url.join("resources").unwrap());
+        let token = SharedSecret::new("token");
+        let site = Url::parse("https://example.atlassian.net/").unwrap();
+
+        // The second lookup is served from the cache.
+        for _ in 0..2 {
+            assert_eq!(
+                client.cloud_id(&token, &site).unwrap().as_deref(),
+                Some("1324a887-45db-1bf4-1e99-ef0ff456d421")
+            );
+        }
+        let other = Url::parse("https://other.atlassian.net").unwrap();
+        assert_eq!(client.cloud_id(&token, &other).unwrap(), None);
+        let resource = &client.list(&token).unwrap()[0];
+        assert!(resource.has_scope("read:jira-work"));
+        assert!(!resource.has_scope("write:jira-work"));
+
+        let requests = server.requests.lock().unwrap();
+        assert_eq!(requests.len(), 1);
+        assert!(requests[0].head.starts_with("GET /resources HTTP/1.1"));
+        assert!(requests[0].head.contains("authorization: Bearer token"));
+        assert!(requests[0].body.is_empty());
+    }
+
+    #[test]
+    fn test_oauth_authorize_url() {
+        let redirect = Url::parse("https://example.com/callback?a=1").unwrap();
+        let url = oauth_authorize_url(
+            "id",
+            &["read:me", "offline_access"],
+            &redirect,
+            "s",
+        )
+        .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://auth.atlassian.com/authorize?audience=api.atlassian.com\
+             &client_id=id&scope=read%3Ame%20offline_access\
+             &redirect_uri=https%3A%2F%2Fexample.com%2Fcallback%3Fa%3D1\
+             &state=s&response_type=code&prompt=consent"
+        );
+    }
+}
@@ -0,0 +1,423 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Random number in `[0, 1)`. This doesn't need to be unpredictable,
+/// just different between clients so that their retries spread out.
+fn random_fraction() -> f64 {
+    let mut hasher = RandomState::new().build_hasher();
+    let nanos = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap_or_default()
+        .subsec_nanos();
+    hasher.write_u32(nanos);
+    (hasher.finish() >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Methods that are retried after a failure that the server may have
+/// processed. DELETE is idempotent, but is left out because retrying
+/// one that succeeded fails with a 404.
+const RETRYABLE_METHODS: [&str; 5] = ["GET", "HEAD", "OPTIONS", "PUT", "TRACE"];
+
+/// When and how often to retry failed requests to Atlassian APIs.
+///
+/// Requests are retried if they fail to connect, or if the response
+/// status is 429 (Too Many Requests), since in both cases the server
+/// has not acted on the request. Other failures, such as timeouts and
+/// the other retryable statuses (by default 502, 503, and 504), may
+/// have happened after the server acted on the request, so they are
+/// only retried for methods that can safely be repeated: GET, HEAD,
+/// OPTIONS, PUT, and TRACE. Use
+/// [`RetryPolicy::with_retry_non_idempotent`] to retry them for every
+/// method.
+///
+/// The delay before each retry grows exponentially from the initial
+/// backoff up to the maximum, and is then reduced by a random amount
+/// up to the jitter fraction so that many clients don't retry in
+/// lockstep. A `Retry-After` header in the response takes precedence
+/// over the computed delay, but is capped at the maximum backoff.
+///
+/// ```
+/// use atlassian_app_auth::RetryPolicy;
+/// use std::time::Duration;
+///
+/// let policy = RetryPolicy::new()
+///     .with_max_attempts(5)
+///     .with_backoff(Duration::from_millis(100), Duration::from_secs(2))
+///     .with_jitter(0.0);
+/// assert_eq!(policy.backoff(1), Duration::from_millis(100));
+/// assert_eq!(policy.backoff(3), Duration::from_millis(400));
+/// assert_eq!(policy.backoff(10), Duration::from_secs(2));
+/// ```
+#[derive(Clone, Debug, PartialEq)]
+pub struct RetryPolicy {
+    max_attempts: u32,
+    initial_backoff: Duration,
+    max_backoff: Duration,
+    multiplier: f64,
+    jitter: f64,
+    retryable_statuses: Vec<u16>,
+    retry_non_idempotent: bool,
+}
+
+impl RetryPolicy {
+    /// Default maximum number of attempts, including the first.
+    pub const DEFAULT_MAX_ATTEMPTS: u32 = 3;
+
+    /// Default delay before the first retry.
+    pub const DEFAULT_INITIAL_BACKOFF: Duration = Duration::from_millis(500);
+
+    /// Default maximum delay between retries.
+    pub const DEFAULT_MAX_BACKOFF: Duration = Duration::from_secs(30);
+
+    /// Statuses that are retried by default.
+    pub const DEFAULT_RETRYABLE_STATUSES: [u16; 4] = [429, 502, 503, 504];
+
+    /// Create a `RetryPolicy` with the default settings.
+    pub fn new() -> RetryPolicy {
+        RetryPolicy {
+            max_attempts: RetryPolicy::DEFAULT_MAX_ATTEMPTS,
+            initial_backoff: RetryPolicy::DEFAULT_INITIAL_BACKOFF,
+            max_backoff: RetryPolicy::DEFAULT_MAX_BACKOFF,
+            multiplier: 2.0,
+            jitter: 0.5,
+            retryable_statuses: RetryPolicy::DEFAULT_RETRYABLE_STATUSES.into(),
+            retry_non_idempotent: false,
+        }
+    }
+
+    /// Create a `RetryPolicy` that never retries.
+    pub fn never() -> RetryPolicy {
+        RetryPolicy::new().with_max_attempts(1)
+    }
+
+    /// Set the maximum number of attempts, including the first. Zero
+    /// is treated as one.
+    pub fn with_max_attempts(mut self, max_attempts: u32) -> RetryPolicy {
+        self.max_attempts = max_attempts.max(1);
+        self
+    }
+
+    /// Set the delay before the first retry, and the maximum delay
+    /// between retries.
+    pub fn with_backoff(
+        mut self,
+        initial: Duration,
+        max: Duration,
+    ) -> RetryPolicy {
+        self.initial_backoff = initial;
+        self.max_backoff = max.max(initial);
+        self
+    }
+
+    /// Set the factor that the delay grows by after each retry. The
+    /// default is 2.
+    pub fn with_multiplier(mut self, multiplier: f64) -> RetryPolicy {
+        self.multiplier = multiplier.max(1.0);
+        self
+    }
+
+    /// Set the largest fraction of each delay that is randomly removed,
+    /// from 0 (no jitter) to 1. The default is 0.5.
+    pub fn with_jitter(mut self, jitter: f64) -> RetryPolicy {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Set the response statuses that are retried.
+    pub fn with_retryable_statuses<S: Into<Vec<u16>>>(
+        mut self,
+        statuses: S,
+    ) -> RetryPolicy {
+        self.retryable_statuses = statuses.into();
+        self
+    }
+
+    /// Retry requests with any method after a timeout or a retryable
+    /// status, not just methods that can safely be repeated. Only
+    /// enable this if repeating a POST, PATCH, or DELETE that may have
+    /// already been processed is harmless.
+    pub fn with_retry_non_idempotent(
+        mut self,
+        retry_non_idempotent: bool,
+    ) -> RetryPolicy {
+        self.retry_non_idempotent = retry_non_idempotent;
+        self
+    }
+
+    /// Maximum number of attempts, including the first.
+    pub fn max_attempts(&self) -> u32 {
+        self.max_attempts
+    }
+
+    /// Whether a response with `status` to a request with `method`
+    /// should be retried. See the type documentation for which methods
+    /// are retried.
+    pub fn is_retryable(&self, method: &str, status: u16) -> bool {
+        self.retryable_statuses.contains(&status)
+            && (status == 429 || self.can_repeat(method))
+    }
+
+    /// Whether a request with `method` can be repeated after a failure
+    /// that the server may have acted on.
+    fn can_repeat(&self, method: &str) -> bool {
+        self.retry_non_idempotent
+            || RETRYABLE_METHODS
+                .iter()
+                .any(|retryable| retryable.eq_ignore_ascii_case(method))
+    }
+
+    /// Delay after the `attempt`th attempt (starting from 1) fails,
+    /// including jitter.
+    pub fn backoff(&self, attempt: u32) -> Duration {
+        let exponent = attempt.saturating_sub(1).min(64) as i32;
+        let backoff =
+            self.initial_backoff.as_secs_f64() * self.multiplier.powi(exponent);
+        let backoff = backoff.min(self.max_backoff.as_secs_f64());
+        let jitter = if self.jitter > 0.0 {
+            self.jitter * random_fraction()
+        } else {
+            0.0
+        };
+        Duration::from_secs_f64(backoff * (1.0 - jitter))
+    }
+
+    /// Delay before retrying after the `attempt`th attempt (starting
+    /// from 1) of a request with `method` got a response with
+    /// `status`, or `None` if the request should not be retried.
+    /// `retry_after` is the value of the response's `Retry-After`
+    /// header, if any; it is capped at the maximum backoff.
+    pub fn retry_delay(
+        &self,
+        attempt: u32,
+        method: &str,
+        status: u16,
+        retry_after: Option<Duration>,
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts || !self.is_retryable(method, status) {
+            return None;
+        }
+        Some(match retry_after {
+            Some(retry_after) => retry_after.min(self.max_backoff),
+            None => self.backoff(attempt),
+        })
+    }
+
+    /// Delay before retrying after the `attempt`th attempt (starting
+    /// from 1) of a request with `method` failed without a response,
+    /// or `None` if the request should not be retried. `timed_out` is
+    /// false if the request failed to connect, so was never sent, and
+    /// true if it timed out, so may have been processed.
+    pub fn connection_retry_delay(
+        &self,
+        attempt: u32,
+        method: &str,
+        timed_out: bool,
+    ) -> Option<Duration> {
+        if attempt >= self.max_attempts
+            || (timed_out && !self.can_repeat(method))
+        {
+            return None;
+        }
+        Some(self.backoff(attempt))
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> RetryPolicy {
+        RetryPolicy::new()
+    }
+}
+
+/// Value of a response's `Retry-After` header, if it is a number of
+/// seconds.
+#[cfg(feature = "client")]
+fn retry_after(headers: &reqwest::header::HeaderMap) -> Option<Duration> {
+    headers
+        .get(reqwest::header::RETRY_AFTER)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse().ok())
+        .map(Duration::from_secs)
+}
+
+/// Check the response's `Date` header for clock drift, if enabled.
+#[cfg(feature = "client")]
+fn check_date(
+    drift: Option<&crate::ClockDriftCheck>,
+    headers: &reqwest::header::HeaderMap,
+) {
+    let date = headers
+        .get(reqwest::header::DATE)
+        .and_then(|value| value.to_str().ok());
+    if let (Some(drift), Some(date)) = (drift, date) {
+        drift.observe_header(date);
+    }
+}
+
+/// Delay before retrying after the `attempt`th attempt of a request
+/// with `method` finished with `result`, or `None` if `result` should
+/// be returned.
+#[cfg(feature = "client")]
+fn next_delay<R>(
+    policy: &RetryPolicy,
+    attempt: u32,
+    method: &reqwest::Method,
+    result: &reqwest::Result<R>,
+    status: impl Fn(&R) -> (u16, Option<Duration>),
+) -> Option<Duration> {
+    match result {
+        Ok(resp) => {
+            let (status, retry_after) = status(resp);
+            policy.retry_delay(attempt, method.as_str(), status, retry_after)
+        }
+        Err(err) if err.is_connect() => {
+            policy.connection_retry_delay(attempt, method.as_str(), false)
+        }
+        Err(err) if err.is_timeout() => {
+            policy.connection_retry_delay(attempt, method.as_str(), true)
+        }
+        Err(_) => None,
+    }
+}
+
+/// Send the request created by `build`, retrying according to
+/// `policy`. `build` is called for each attempt so that every attempt
+/// gets a freshly signed token, and must use `method`. If `drift` is
+/// set, every response is checked for clock drift.
+#[cfg(feature = "client")]
+pub(crate) fn send(
+    policy: &RetryPolicy,
+    drift: Option<&crate::ClockDriftCheck>,
+    method: &reqwest::Method,
+    mut build: impl FnMut() -> Result<
+        reqwest::blocking::RequestBuilder,
+        crate::AuthError,
+    >,
+) -> Result<reqwest::blocking::Response, crate::AuthError> {
+    let mut attempt = 1;
+    loop {
+        let result = build()?.send();
+        let delay = next_delay(policy, attempt, method, &result, |resp| {
+            check_date(drift, resp.headers());
+            (resp.status().as_u16(), retry_after(resp.headers()))
+        });
+        match delay {
+            Some(delay) => std::thread::sleep(delay),
+            None => {
+                let resp = result.map_err(crate::request_error)?;
+                let status = resp.status();
+                if status.is_client_error() || status.is_server_error() {
+                    let body = resp.text().unwrap_or_default();
+                    return Err(
+                        crate::ApiError::parse(status.as_u16(), &body).into()
+                    );
+                }
+                return Ok(resp);
+            }
+        }
+        attempt += 1;
+    }
+}
+
+/// Async version of [`send`].
+#[cfg(feature = "client")]
+pub(crate) async fn send_async<F, Fut>(
+    policy: &RetryPolicy,
+    drift: Option<&crate::ClockDriftCheck>,
+    method: &reqwest::Method,
+    mut build: F,
+) -> Result<reqwest::Response, crate::AuthError>
+where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<
+        Output = Result<reqwest::RequestBuilder, crate::AuthError>,
+    >,
+{
+    let mut attempt = 1;
+    loop {
+        let result = build().await?.send().await;
+        let delay = next_delay(policy, attempt, method, &result, |resp| {
+            check_date(drift, resp.headers());
+            (resp.status().as_u16(), retry_after(resp.headers()))
+        });
+        match delay {
+            Some(delay) => tokio::time::sleep(delay).await,
+            None => {
+                let resp = result.map_err(crate::request_error)?;
+                let status = resp.status();
+                if status.is_client_error() || status.is_server_error() {
+                    let body = resp.text().await.unwrap_or_default();
+                    return Err(
+                        crate::ApiError::parse(status.as_u16(), &body).into()
+                    );
+                }
+                return Ok(resp);
+            }
+        }
+        attempt += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_retry_policy() {
+        let policy = RetryPolicy::new()
+            .with_backoff(Duration::from_secs(1), Duration::from_secs(5))
+            .with_jitter(0.0);
+        assert_eq!(policy.backoff(1), Duration::from_secs(1));
+        assert_eq!(policy.backoff(2), Duration::from_secs(2));
+        assert_eq!(policy.backoff(3), Duration::from_secs(4));
+        assert_eq!(policy.backoff(4), Duration::from_secs(5));
+        assert_eq!(policy.backoff(u32::MAX), Duration::from_secs(5));
+
+        assert_eq!(
+            policy.retry_delay(1, "GET", 503, None),
+            Some(policy.backoff(1))
+        );
+        assert_eq!(
+            policy.retry_delay(2, "GET", 429, Some(Duration::from_secs(3))),
+            Some(Duration::from_secs(3))
+        );
+        // Retry-After is capped at the maximum backoff.
+        assert_eq!(
+            policy.retry_delay(2, "GET", 429, Some(Duration::from_secs(3600))),
+            Some(Duration::from_secs(5))
+        );
+        // Not retryable
+        assert_eq!(policy.retry_delay(1, "GET", 500, None), None);
+        // Out of attempts
+        assert_eq!(policy.retry_delay(3, "GET", 503, None), None);
+        assert_eq!(RetryPolicy::never().retry_delay(1, "GET", 503, None), None);
+
+        // Requests that may have been processed are only retried for
+        // methods that can be repeated, unless the caller opts in.
+        assert!(policy.retry_delay(1, "put", 503, None).is_some());
+        for method in ["POST", "PATCH", "DELETE"] {
+            assert_eq!(policy.retry_delay(1, method, 503, None), None);
+            assert!(policy.retry_delay(1, method, 429, None).is_some());
+        }
+        let opted_in = policy.clone().with_retry_non_idempotent(true);
+        assert!(opted_in.retry_delay(1, "POST", 503, None).is_some());
+
+        // Connection errors are always retried, timeouts only for
+        // methods that can be repeated.
+        assert_eq!(
+            policy.connection_retry_delay(1, "POST", false),
+            Some(policy.backoff(1))
+        );
+        assert!(policy.connection_retry_delay(1, "GET", true).is_some());
+        assert_eq!(policy.connection_retry_delay(1, "POST", true), None);
+        assert!(opted_in.connection_retry_delay(1, "POST", true).is_some());
+        assert_eq!(policy.connection_retry_delay(3, "GET", false), None);
+
+        let jittered = policy.with_jitter(0.5);
+        for attempt in 1..5 {
+            let delay = jittered.backoff(attempt);
+            let full = jittered.clone().with_jitter(0.0).backoff(attempt);
+            assert!(delay <= full && delay >= full / 2);
+        }
+    }
+}
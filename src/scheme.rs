@@ -0,0 +1,296 @@
+use crate::{create_bearer_auth_header, Header, SharedSecret};
+use std::fmt;
+use url::Url;
+
+/// Query parameters that hold Trello credentials.
+const TRELLO_PARAMS: [&str; 2] = ["key", "token"];
+
+/// Authentication for Atlassian products that don't use Connect JWTs.
+///
+/// Schemes add credentials to a request as a [`Header`] (see
+/// [`AuthScheme::header`]), in the query string (see
+/// [`AuthScheme::authenticate_url`]), or both; call both methods for
+/// every request so that any scheme can be used.
+///
+/// Like [`SharedSecret`], the [`Debug`] implementation does not print
+/// the credentials.
+///
+/// ```
+/// use atlassian_app_auth::{AuthScheme, SharedSecret};
+/// use url::Url;
+///
+/// let scheme = AuthScheme::TrelloKeyToken {
+///     key: "api-key".into(),
+///     token: SharedSecret::new("token"),
+/// };
+/// let url = Url::parse("https://api.trello.com/1/members/me").unwrap();
+/// let url = scheme.authenticate_url(&url);
+/// assert_eq!(
+///     url.to_string(),
+///     "https://api.trello.com/1/members/me?key=REDACTED&token=REDACTED"
+/// );
+/// assert!(scheme.header().is_none());
+/// ```
+#[derive(Clone)]
+#[non_exhaustive]
+pub enum AuthScheme {
+    /// Trello API key and user token, sent as the `key` and `token`
+    /// query parameters.
+    TrelloKeyToken {
+        /// API key of the Power-Up or integration.
+        key: String,
+        /// Token that a user has granted to the key.
+        token: SharedSecret,
+    },
+
+    /// Opsgenie API key, sent as `Authorization: GenieKey <key>`.
+    OpsgenieApiKey {
+        /// API key of an Opsgenie API integration.
+        key: SharedSecret,
+    },
+
+    /// Bitbucket Cloud username and app password, sent with HTTP
+    /// Basic authentication. Requires the `bitbucket-app-password`
+    /// feature.
+    #[cfg(feature = "bitbucket-app-password")]
+    BitbucketAppPassword {
+        /// Bitbucket username (not the email address).
+        username: String,
+        /// App password created by the user.
+        app_password: SharedSecret,
+    },
+
+    /// Bitbucket Cloud workspace, project, or repository access token,
+    /// sent as `Authorization: Bearer <token>`.
+    BitbucketAccessToken {
+        /// The access token.
+        token: SharedSecret,
+    },
+
+    /// Statuspage API key, sent as `Authorization: OAuth <key>`.
+    StatuspageApiKey {
+        /// API key of a Statuspage user.
+        key: SharedSecret,
+    },
+}
+
+impl AuthScheme {
+    /// Create the authentication [`Header`] for a request, or `None`
+    /// if the scheme doesn't use one.
+    pub fn header(&self) -> Option<Header> {
+        match self {
+            AuthScheme::TrelloKeyToken { .. } => None,
+            AuthScheme::OpsgenieApiKey { key } => Some(Header {
+                name: "Authorization",
+                value: format!("GenieKey {}", key.expose()),
+            }),
+            #[cfg(feature = "bitbucket-app-password")]
+            AuthScheme::BitbucketAppPassword {
+                username,
+                app_password,
+            } => Some(Header {
+                name: "Authorization",
+                value: format!(
+                    "Basic {}",
+                    base64::encode(format!(
+                        "{}:{}",
+                        username,
+                        app_password.expose()
+                    ))
+                ),
+            }),
+            AuthScheme::BitbucketAccessToken { token } => {
+                Some(create_bearer_auth_header(token))
+            }
+            AuthScheme::StatuspageApiKey { key } => Some(Header {
+                name: "Authorization",
+                value: format!("OAuth {}", key.expose()),
+            }),
+        }
+    }
+
+    /// Add any query string credentials to `url`. Credential parameters
+    /// already in `url` are replaced rather than sent twice.
+    pub fn authenticate_url(&self, url: &Url) -> AuthenticatedUrl {
+        let mut url = url.clone();
+        let secret_params: &'static [&'static str] = match self {
+            AuthScheme::TrelloKeyToken { key, token } => {
+                set_query_params(
+                    &mut url,
+                    &[("key", key), ("token", token.expose())],
+                );
+                &TRELLO_PARAMS
+            }
+            AuthScheme::OpsgenieApiKey { .. }
+            | AuthScheme::BitbucketAccessToken { .. }
+            | AuthScheme::StatuspageApiKey { .. } => &[],
+            #[cfg(feature = "bitbucket-app-password")]
+            AuthScheme::BitbucketAppPassword { .. } => &[],
+        };
+        AuthenticatedUrl { url, secret_params }
+    }
+}
+
+impl fmt::Debug for AuthScheme {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            AuthScheme::TrelloKeyToken { .. } => {
+                f.debug_struct("TrelloKeyToken").finish_non_exhaustive()
+            }
+            AuthScheme::OpsgenieApiKey { .. } => {
+                f.debug_struct("OpsgenieApiKey").finish_non_exhaustive()
+            }
+            #[cfg(feature = "bitbucket-app-password")]
+            AuthScheme::BitbucketAppPassword { .. } => f
+                .debug_struct("BitbucketAppPassword")
+                .finish_non_exhaustive(),
+            AuthScheme::BitbucketAccessToken { .. } => f
+                .debug_struct("BitbucketAccessToken")
+                .finish_non_exhaustive(),
+            AuthScheme::StatuspageApiKey { .. } => {
+                f.debug_struct("StatuspageApiKey").finish_non_exhaustive()
+            }
+        }
+    }
+}
+
+/// Replace the query parameters named in `params` with `params`.
+fn set_query_params(url: &mut Url, params: &[(&str, &str)]) {
+    let kept: Vec<(String, String)> = url
+        .query_pairs()
+        .filter(|(name, _)| params.iter().all(|(param, _)| name != param))
+        .map(|(name, value)| (name.into_owned(), value.into_owned()))
+        .collect();
+    url.query_pairs_mut()
+        .clear()
+        .extend_pairs(kept)
+        .extend_pairs(params);
+}
+
+/// URL that contains credentials, created by
+/// [`AuthScheme::authenticate_url`].
+///
+/// The [`Debug`] and [`Display`] implementations replace credential
+/// values with `REDACTED`, so the URL can be logged; use
+/// [`AuthenticatedUrl::expose`] to get the URL to send.
+///
+/// [`Display`]: fmt::Display
+#[derive(Clone, PartialEq, Eq)]
+pub struct AuthenticatedUrl {
+    url: Url,
+    /// Names of the query parameters that hold credentials.
+    secret_params: &'static [&'static str],
+}
+
+impl AuthenticatedUrl {
+    /// Get the URL, including credentials.
+    pub fn expose(&self) -> &Url {
+        &self.url
+    }
+
+    fn redacted(&self) -> Url {
+        let mut url = self.url.clone();
+        let redacted: Vec<(&str, &str)> = self
+            .secret_params
+            .iter()
+            .filter(|param| url.query_pairs().any(|(name, _)| name == **param))
+            .map(|param| (*param, "REDACTED"))
+            .collect();
+        if !redacted.is_empty() {
+            set_query_params(&mut url, &redacted);
+        }
+        url
+    }
+}
+
+impl fmt::Debug for AuthenticatedUrl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("AuthenticatedUrl")
+            .field(&self.redacted().as_str())
+            .finish()
+    }
+}
+
+impl fmt::Display for AuthenticatedUrl {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.redacted().as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_trello_key_token() {
+        let scheme = AuthScheme::TrelloKeyToken {
+            key: "k&ey".into(),
+            token: SharedSecret::new("secret-token"),
+        };
+        let url = Url::parse(
+            "https://api.trello.com/1/boards/abc/cards?token=old&fields=name",
+        )
+        .unwrap();
+        let url = scheme.authenticate_url(&url);
+        assert_eq!(
+            url.expose().as_str(),
+            "https://api.trello.com/1/boards/abc/cards\
+             ?fields=name&key=k%26ey&token=secret-token"
+        );
+        assert_eq!(
+            format!("{:?}", url),
+            "AuthenticatedUrl(\"https://api.trello.com/1/boards/abc/cards\
+             ?fields=name&key=REDACTED&token=REDACTED\")"
+        );
+        assert!(!url.to_string().contains("secret-token"));
+        assert_eq!(format!("{:?}", scheme), "TrelloKeyToken { .. }");
+    }
+
+    #[test]
+    fn test_opsgenie_api_key() {
+        let scheme = AuthScheme::OpsgenieApiKey {
+            key: SharedSecret::new("genie"),
+        };
+        let header = scheme.header().unwrap();
+        assert_eq!(header.name, "Authorization");
+        assert_eq!(header.value, "GenieKey genie");
+
+        let url = Url::parse("https://api.opsgenie.com/v2/alerts?a=1").unwrap();
+        let authenticated = scheme.authenticate_url(&url);
+        assert_eq!(authenticated.expose(), &url);
+        assert_eq!(authenticated.to_string(), url.as_str());
+        assert_eq!(format!("{:?}", scheme), "OpsgenieApiKey { .. }");
+    }
+
+    #[cfg(feature = "bitbucket-app-password")]
+    #[test]
+    fn test_bitbucket_app_password() {
+        let scheme = AuthScheme::BitbucketAppPassword {
+            username: "user".into(),
+            app_password: SharedSecret::new("pass"),
+        };
+        let header = scheme.header().unwrap();
+        assert_eq!(header.name, "Authorization");
+        assert_eq!(header.value, "Basic dXNlcjpwYXNz");
+    }
+
+    #[test]
+    fn test_bitbucket_access_token() {
+        let scheme = AuthScheme::BitbucketAccessToken {
+            token: SharedSecret::new("token"),
+        };
+        assert_eq!(scheme.header().unwrap().value, "Bearer token");
+        assert_eq!(format!("{:?}", scheme), "BitbucketAccessToken { .. }");
+    }
+
+    #[test]
+    fn test_statuspage_api_key() {
+        let scheme = AuthScheme::StatuspageApiKey {
+            key: SharedSecret::new("key"),
+        };
+        let header = scheme.header().unwrap();
+        assert_eq!(header.name, "Authorization");
+        assert_eq!(header.value, "OAuth key");
+        assert_eq!(format!("{:?}", scheme), "StatuspageApiKey { .. }");
+    }
+}
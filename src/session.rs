@@ -0,0 +1,221 @@
+#[cfg(feature = "verify")]
+use crate::verify::decode_error;
+#[cfg(feature = "verify")]
+use crate::VerificationFailure;
+use crate::{AuthError, Clock, SharedSecret, SystemClock};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Claims of a session token issued by [`SessionTokens`].
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SessionClaims {
+    /// Issuer of the token. This is the app, not Atlassian.
+    pub iss: String,
+
+    /// Atlassian account ID of the user.
+    pub sub: String,
+
+    /// Client key of the tenant the user belongs to.
+    pub tenant: String,
+
+    /// The time that the token was issued.
+    pub iat: u64,
+
+    /// Token expiration time.
+    pub exp: u64,
+}
+
+/// Issues and verifies the app's own session tokens.
+///
+/// A common pattern for Connect apps is to verify the context JWT
+/// that Atlassian passes to the app's iframe (see
+/// `Verifier::verify_context_token`), then hand the frontend a
+/// short-lived token of the app's own for calls back to the app's
+/// backend. These tokens are signed with a secret known only to the
+/// app, and identify the user by account ID and tenant by client key.
+///
+/// Session tokens are not accepted by Atlassian, and Atlassian tokens
+/// are not accepted by `SessionTokens::verify` unless they have the
+/// same issuer and secret, so use a secret that is not any tenant's
+/// shared secret.
+#[derive(Clone)]
+pub struct SessionTokens {
+    issuer: String,
+    secret: SharedSecret,
+    valid_for: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl SessionTokens {
+    /// Default duration that session tokens are valid for.
+    pub const DEFAULT_VALID_FOR: Duration = Duration::from_secs(15 * 60);
+
+    /// Create a new `SessionTokens`. `issuer` identifies the app in
+    /// the `iss` claim, and `secret` is used to sign the tokens.
+    pub fn new<S: Into<String>>(
+        issuer: S,
+        secret: SharedSecret,
+    ) -> SessionTokens {
+        SessionTokens {
+            issuer: issuer.into(),
+            secret,
+            valid_for: SessionTokens::DEFAULT_VALID_FOR,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Set the duration that tokens will be valid for.
+    pub fn with_valid_for(mut self, valid_for: Duration) -> SessionTokens {
+        self.valid_for = valid_for;
+        self
+    }
+
+    /// Use `clock` to get the current time instead of [`SystemClock`].
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> SessionTokens {
+        self.clock = clock;
+        self
+    }
+
+    /// Issue a token for the user identified by `account_id` in the
+    /// tenant identified by `tenant`.
+    pub fn issue(
+        &self,
+        tenant: &str,
+        account_id: &str,
+    ) -> Result<String, AuthError> {
+        let now = crate::clock::unix_time(&*self.clock)?;
+        let claims = SessionClaims {
+            iss: self.issuer.clone(),
+            sub: account_id.into(),
+            tenant: tenant.into(),
+            iat: now,
+            exp: now + self.valid_for.as_secs(),
+        };
+        Ok(jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &claims,
+            &jsonwebtoken::EncodingKey::from_secret(
+                self.secret.expose().as_bytes(),
+            ),
+        )?)
+    }
+
+    /// Verify a token issued by [`SessionTokens::issue`], checking its
+    /// signature, issuer, and expiration time.
+    #[cfg(feature = "verify")]
+    pub fn verify(&self, token: &str) -> Result<SessionClaims, AuthError> {
+        let validation = jsonwebtoken::Validation {
+            validate_exp: false,
+            ..jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::HS256)
+        };
+        let claims = jsonwebtoken::decode::<SessionClaims>(
+            token,
+            &jsonwebtoken::DecodingKey::from_secret(
+                self.secret.expose().as_bytes(),
+            ),
+            &validation,
+        )
+        .map_err(decode_error)?
+        .claims;
+
+        if claims.iss != self.issuer {
+            return Err(AuthError::VerificationFailed {
+                reason: VerificationFailure::InvalidIssuer,
+            });
+        }
+        if claims.exp < crate::clock::unix_time(&*self.clock)? {
+            return Err(AuthError::VerificationFailed {
+                reason: VerificationFailure::Expired,
+            });
+        }
+        Ok(claims)
+    }
+}
+
+impl fmt::Debug for SessionTokens {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SessionTokens")
+            .field("issuer", &self.issuer)
+            .field("secret", &self.secret)
+            .field("valid_for", &self.valid_for)
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::UNIX_EPOCH;
+
+    #[test]
+    fn test_issue() {
+        let sessions = SessionTokens::new("app", SharedSecret::new("secret"))
+            .with_valid_for(Duration::from_secs(60))
+            .with_clock(Arc::new(|| UNIX_EPOCH + Duration::from_secs(1000)));
+
+        let token = sessions.issue("tenant", "account").unwrap();
+        assert_eq!(
+            jsonwebtoken::dangerous_insecure_decode::<SessionClaims>(&token)
+                .unwrap()
+                .claims,
+            SessionClaims {
+                iss: "app".into(),
+                sub: "account".into(),
+                tenant: "tenant".into(),
+                iat: 1000,
+                exp: 1060,
+            }
+        );
+    }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn test_session_tokens() {
+        let at = |secs| move || UNIX_EPOCH + Duration::from_secs(secs);
+        let sessions = SessionTokens::new("app", SharedSecret::new("secret"))
+            .with_valid_for(Duration::from_secs(60))
+            .with_clock(Arc::new(at(1000)));
+
+        let token = sessions.issue("tenant", "account").unwrap();
+        assert_eq!(
+            sessions.verify(&token).unwrap(),
+            SessionClaims {
+                iss: "app".into(),
+                sub: "account".into(),
+                tenant: "tenant".into(),
+                iat: 1000,
+                exp: 1060,
+            }
+        );
+
+        let err = sessions
+            .clone()
+            .with_clock(Arc::new(at(1061)))
+            .verify(&token)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            AuthError::VerificationFailed {
+                reason: VerificationFailure::Expired
+            }
+        ));
+
+        let other = SessionTokens::new("other", SharedSecret::new("secret"));
+        assert!(matches!(
+            other.verify(&token),
+            Err(AuthError::VerificationFailed {
+                reason: VerificationFailure::InvalidIssuer
+            })
+        ));
+
+        let wrong = SessionTokens::new("app", SharedSecret::new("wrong"));
+        assert!(matches!(
+            wrong.verify(&token),
+            Err(AuthError::VerificationFailed {
+                reason: VerificationFailure::InvalidSignature
+            })
+        ));
+    }
+}
@@ -0,0 +1,445 @@
+#[cfg(feature = "verify")]
+use crate::verify::{decode_and_verify, unverified_issuer, ExpectedQsh};
+#[cfg(feature = "verify")]
+use crate::Verifier;
+use crate::{
+    AuthError, AuthEvent, AuthEventKind, AuthEventSink, Clock, Header,
+    SharedSecret, Signer, SystemClock,
+};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::hash::BuildHasher;
+use std::sync::Arc;
+use std::time::Duration;
+use url::Url;
+
+/// Source of per-tenant shared secrets.
+///
+/// Each Atlassian site that installs the app is a separate tenant,
+/// identified by the "clientKey" field of the installation lifecycle
+/// callback. Each tenant has its own shared secret.
+pub trait TenantStore {
+    /// Get the shared secret for the tenant identified by
+    /// `client_key`, or `None` if the tenant is not known.
+    fn shared_secret(
+        &self,
+        client_key: &str,
+    ) -> Result<Option<SharedSecret>, AuthError>;
+}
+
+impl<S: BuildHasher> TenantStore for HashMap<String, SharedSecret, S> {
+    fn shared_secret(
+        &self,
+        client_key: &str,
+    ) -> Result<Option<SharedSecret>, AuthError> {
+        Ok(self.get(client_key).cloned())
+    }
+}
+
+/// Async version of [`TenantStore`], for stores that need to do I/O
+/// such as a database query.
+///
+/// A synchronous store can be used where an `AsyncTenantStore` is
+/// required by wrapping it in [`SyncTenantStore`].
+pub trait AsyncTenantStore {
+    /// Get the shared secret for the tenant identified by
+    /// `client_key`, or `None` if the tenant is not known.
+    fn shared_secret(
+        &self,
+        client_key: &str,
+    ) -> impl Future<Output = Result<Option<SharedSecret>, AuthError>> + Send;
+}
+
+/// Adapter that implements [`AsyncTenantStore`] for a [`TenantStore`].
+///
+/// Lookups run directly on the calling task, so this should only
+/// wrap stores that don't block, such as a `HashMap`.
+#[derive(Clone, Debug, Default)]
+pub struct SyncTenantStore<S>(pub S);
+
+impl<S: TenantStore + Sync> AsyncTenantStore for SyncTenantStore<S> {
+    fn shared_secret(
+        &self,
+        client_key: &str,
+    ) -> impl Future<Output = Result<Option<SharedSecret>, AuthError>> + Send
+    {
+        let result = self.0.shared_secret(client_key);
+        async move { result }
+    }
+}
+
+/// Verify a JWT sent by Atlassian, using the shared secret of the
+/// tenant that issued the token.
+///
+/// The tenant's client key (the "iss" claim) is used to look up the
+/// shared secret in `store`, then the token is checked as described
+/// in [`verify_token`]. On success the client key is returned.
+///
+/// [`verify_token`]: crate::verify_token
+#[cfg(feature = "verify")]
+pub fn verify_tenant_token<S: TenantStore + ?Sized>(
+    store: &S,
+    token: &str,
+    method: &str,
+    url: &Url,
+) -> Result<String, AuthError> {
+    let client_key = unverified_issuer(token)?;
+    let shared_secret = store
+        .shared_secret(&client_key)?
+        .ok_or(AuthError::UnknownTenant)?;
+    let claims = decode_and_verify(
+        token,
+        ExpectedQsh::Request { method, url },
+        &shared_secret,
+        &SystemClock,
+    )?;
+    Ok(claims.iss)
+}
+
+/// Signs and verifies requests for multiple tenants, using shared
+/// secrets from a [`TenantStore`] or [`AsyncTenantStore`].
+///
+/// The sync methods are available when `S` is a [`TenantStore`], and
+/// the `_async` methods when `S` is an [`AsyncTenantStore`].
+pub struct TenantAuth<S> {
+    app_key: String,
+    store: S,
+    valid_for: Duration,
+    clock: Arc<dyn Clock>,
+    event_sink: Option<Arc<dyn AuthEventSink>>,
+}
+
+impl<S> TenantAuth<S> {
+    /// Create a new `TenantAuth`. `app_key` is the key of the Connect
+    /// App, used as the issuer of outgoing tokens.
+    pub fn new<K: Into<String>>(app_key: K, store: S) -> TenantAuth<S> {
+        TenantAuth {
+            app_key: app_key.into(),
+            store,
+            valid_for: Signer::DEFAULT_VALID_FOR,
+            clock: Arc::new(SystemClock),
+            event_sink: None,
+        }
+    }
+
+    /// Set the duration that outgoing tokens will be valid for. See
+    /// [`Signer::with_valid_for`].
+    pub fn with_valid_for(mut self, valid_for: Duration) -> TenantAuth<S> {
+        self.valid_for = valid_for;
+        self
+    }
+
+    /// Use `clock` to get the current time instead of [`SystemClock`].
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> TenantAuth<S> {
+        self.clock = clock;
+        self
+    }
+
+    /// Send an [`AuthEvent`] to `sink` every time a token is created
+    /// or verified.
+    pub fn with_event_sink(
+        mut self,
+        sink: Arc<dyn AuthEventSink>,
+    ) -> TenantAuth<S> {
+        self.event_sink = Some(sink);
+        self
+    }
+
+    /// Get the underlying store.
+    pub fn store(&self) -> &S {
+        &self.store
+    }
+
+    fn record_failure(
+        &self,
+        kind: AuthEventKind,
+        tenant: Option<&str>,
+        method: &str,
+        url: &Url,
+        err: &AuthError,
+    ) {
+        if let Some(sink) = &self.event_sink {
+            sink.record(&AuthEvent::new(
+                kind,
+                tenant,
+                method,
+                url.host_str(),
+                url.path(),
+                Err(err),
+            ));
+        }
+    }
+
+    fn sign(
+        &self,
+        client_key: &str,
+        shared_secret: Result<Option<SharedSecret>, AuthError>,
+        method: &str,
+        url: &Url,
+    ) -> Result<Header, AuthError> {
+        let shared_secret = match shared_secret
+            .and_then(|s| s.ok_or(AuthError::UnknownTenant))
+        {
+            Ok(shared_secret) => shared_secret,
+            Err(err) => {
+                self.record_failure(
+                    AuthEventKind::Sign,
+                    Some(client_key),
+                    method,
+                    url,
+                    &err,
+                );
+                return Err(err);
+            }
+        };
+
+        let mut signer = Signer::new(self.app_key.clone(), shared_secret)
+            .with_valid_for(self.valid_for)
+            .with_clock(self.clock.clone())
+            .with_tenant(client_key.into());
+        if let Some(sink) = &self.event_sink {
+            signer = signer.with_event_sink(sink.clone());
+        }
+        signer.create_auth_header(method, url)
+    }
+
+    #[cfg(feature = "verify")]
+    fn verify_with(
+        &self,
+        client_key: &str,
+        shared_secret: Result<Option<SharedSecret>, AuthError>,
+        token: &str,
+        method: &str,
+        url: &Url,
+    ) -> Result<String, AuthError> {
+        let shared_secret = match shared_secret
+            .and_then(|s| s.ok_or(AuthError::UnknownTenant))
+        {
+            Ok(shared_secret) => shared_secret,
+            Err(err) => {
+                self.record_failure(
+                    AuthEventKind::Verify,
+                    Some(client_key),
+                    method,
+                    url,
+                    &err,
+                );
+                return Err(err);
+            }
+        };
+
+        let mut verifier =
+            Verifier::new(shared_secret).with_clock(self.clock.clone());
+        if let Some(sink) = &self.event_sink {
+            verifier = verifier.with_event_sink(sink.clone());
+        }
+        Ok(verifier.verify_claims(token, method, url)?.iss)
+    }
+
+    #[cfg(feature = "verify")]
+    fn issuer(
+        &self,
+        token: &str,
+        method: &str,
+        url: &Url,
+    ) -> Result<String, AuthError> {
+        unverified_issuer(token).inspect_err(|err| {
+            self.record_failure(AuthEventKind::Verify, None, method, url, err)
+        })
+    }
+}
+
+impl<S: TenantStore> TenantAuth<S> {
+    /// Create an authentication [`Header`] for a request to the tenant
+    /// identified by `client_key`.
+    pub fn create_auth_header(
+        &self,
+        client_key: &str,
+        method: &str,
+        url: &Url,
+    ) -> Result<Header, AuthError> {
+        let shared_secret = self.store.shared_secret(client_key);
+        self.sign(client_key, shared_secret, method, url)
+    }
+
+    /// Verify a JWT sent by Atlassian. See [`verify_tenant_token`] for
+    /// details. On success the tenant's client key is returned.
+    #[cfg(feature = "verify")]
+    pub fn verify(
+        &self,
+        token: &str,
+        method: &str,
+        url: &Url,
+    ) -> Result<String, AuthError> {
+        let client_key = self.issuer(token, method, url)?;
+        let shared_secret = self.store.shared_secret(&client_key);
+        self.verify_with(&client_key, shared_secret, token, method, url)
+    }
+}
+
+impl<S: AsyncTenantStore> TenantAuth<S> {
+    /// Async version of [`TenantAuth::create_auth_header`].
+    pub async fn create_auth_header_async(
+        &self,
+        client_key: &str,
+        method: &str,
+        url: &Url,
+    ) -> Result<Header, AuthError> {
+        let shared_secret = self.store.shared_secret(client_key).await;
+        self.sign(client_key, shared_secret, method, url)
+    }
+
+    /// Async version of [`TenantAuth::verify`].
+    #[cfg(feature = "verify")]
+    pub async fn verify_async(
+        &self,
+        token: &str,
+        method: &str,
+        url: &Url,
+    ) -> Result<String, AuthError> {
+        let client_key = self.issuer(token, method, url)?;
+        let shared_secret = self.store.shared_secret(&client_key).await;
+        self.verify_with(&client_key, shared_secret, token, method, url)
+    }
+}
+
+impl<S: fmt::Debug> fmt::Debug for TenantAuth<S> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TenantAuth")
+            .field("app_key", &self.app_key)
+            .field("store", &self.store)
+            .field("valid_for", &self.valid_for)
+            .field("event_sink", &self.event_sink.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::pin::pin;
+    use std::task::{Context, Poll, Wake};
+
+    /// Run a future that never has to wait to completion.
+    fn block_on<F: Future>(future: F) -> F::Output {
+        struct NoopWaker;
+        impl Wake for NoopWaker {
+            fn wake(self: Arc<Self>) {}
+        }
+
+        let waker = Arc::new(NoopWaker).into();
+        let mut cx = Context::from_waker(&waker);
+        let mut future = pin!(future);
+        loop {
+            if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+                return output;
+            }
+        }
+    }
+
+    fn store() -> HashMap<String, SharedSecret> {
+        let mut store = HashMap::new();
+        store.insert("tenant-a".to_string(), SharedSecret::new("secret-a"));
+        store
+    }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn test_tenant_auth() {
+        let auth = TenantAuth::new("app", store());
+        let url = Url::parse("https://example.com/example").unwrap();
+
+        let header = auth.create_auth_header("tenant-a", "GET", &url).unwrap();
+        let token = header.value.strip_prefix("JWT ").unwrap();
+
+        // Tokens from the app use the app key as the issuer, so use a
+        // store keyed by app key to check the round trip.
+        let mut app_store = HashMap::new();
+        app_store.insert("app".to_string(), SharedSecret::new("secret-a"));
+        let verifier = TenantAuth::new("app", app_store);
+        assert_eq!(verifier.verify(token, "GET", &url).unwrap(), "app");
+    }
+
+    #[test]
+    fn test_unknown_tenant() {
+        let url = Url::parse("https://example.com/example").unwrap();
+
+        let auth = TenantAuth::new("app", store());
+        auth.create_auth_header("tenant-a", "GET", &url).unwrap();
+        assert!(matches!(
+            auth.create_auth_header("tenant-b", "GET", &url),
+            Err(AuthError::UnknownTenant)
+        ));
+
+        let auth = TenantAuth::new("app", SyncTenantStore(store()));
+        assert!(matches!(
+            block_on(auth.create_auth_header_async("tenant-b", "GET", &url)),
+            Err(AuthError::UnknownTenant)
+        ));
+    }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn test_tenant_auth_async() {
+        let auth = TenantAuth::new("tenant-a", SyncTenantStore(store()));
+        let url = Url::parse("https://example.com/example").unwrap();
+
+        let header =
+            block_on(auth.create_auth_header_async("tenant-a", "GET", &url))
+                .unwrap();
+        let token = header.value.strip_prefix("JWT ").unwrap();
+        assert_eq!(
+            block_on(auth.verify_async(token, "GET", &url)).unwrap(),
+            "tenant-a"
+        );
+        assert!(matches!(
+            block_on(auth.verify_async(token, "POST", &url)),
+            Err(AuthError::QshMismatch { .. })
+        ));
+    }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn test_verify_tenant_token() {
+        let mut store = HashMap::new();
+        store.insert("tenant-a".to_string(), SharedSecret::new("secret-a"));
+
+        let url = Url::parse("https://example.com/example").unwrap();
+        let token = |client_key: &str, secret: &str| {
+            let header = Signer::new(client_key, SharedSecret::new(secret))
+                .create_auth_header("GET", &url)
+                .unwrap();
+            header.value.strip_prefix("JWT ").unwrap().to_string()
+        };
+
+        assert_eq!(
+            verify_tenant_token(
+                &store,
+                &token("tenant-a", "secret-a"),
+                "GET",
+                &url
+            )
+            .unwrap(),
+            "tenant-a"
+        );
+        assert!(matches!(
+            verify_tenant_token(
+                &store,
+                &token("tenant-b", "secret-a"),
+                "GET",
+                &url
+            ),
+            Err(AuthError::UnknownTenant)
+        ));
+        assert!(matches!(
+            verify_tenant_token(
+                &store,
+                &token("tenant-a", "secret-b"),
+                "GET",
+                &url
+            ),
+            Err(AuthError::VerificationFailed { .. })
+        ));
+    }
+}
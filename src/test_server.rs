@@ -0,0 +1,267 @@
+use crate::{AuthError, SharedSecret, Verifier};
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread;
+use url::Url;
+
+/// Request received by a [`MockAtlassianServer`].
+#[derive(Clone, Debug)]
+pub struct ReceivedRequest {
+    /// HTTP method.
+    pub method: String,
+
+    /// Request target, i.e. the path and query string.
+    pub target: String,
+
+    /// Request body.
+    pub body: String,
+
+    /// Issuer of the request's token if it was accepted, or the reason
+    /// it was rejected.
+    pub auth: Result<String, String>,
+}
+
+type Routes = HashMap<(String, String), (u16, String)>;
+
+struct State {
+    url: Url,
+    app_key: String,
+    verifier: Verifier,
+    routes: Mutex<Routes>,
+    requests: Mutex<Vec<ReceivedRequest>>,
+    shutdown: AtomicBool,
+}
+
+impl State {
+    fn authenticate(
+        &self,
+        method: &str,
+        target: &str,
+        authorization: Option<&str>,
+    ) -> Result<String, AuthError> {
+        let token = authorization
+            .and_then(|value| value.strip_prefix("JWT "))
+            .ok_or(AuthError::VerificationFailed {
+                reason: crate::VerificationFailure::Malformed,
+            })?;
+        let url = self.url.join(target).map_err(|_| AuthError::InvalidUrl {
+            reason: "invalid request target",
+        })?;
+        let claims = self.verifier.verify_claims(token, method, &url)?;
+        if claims.iss != self.app_key {
+            return Err(AuthError::UnknownTenant);
+        }
+        Ok(claims.iss)
+    }
+
+    fn handle(&self, stream: TcpStream) -> io::Result<()> {
+        let mut reader = BufReader::new(stream.try_clone()?);
+
+        let mut request_line = String::new();
+        reader.read_line(&mut request_line)?;
+        let mut parts = request_line.split_whitespace();
+        let method = parts.next().unwrap_or_default().to_string();
+        let target = parts.next().unwrap_or_default().to_string();
+
+        let mut authorization = None;
+        let mut content_length = 0;
+        loop {
+            let mut line = String::new();
+            reader.read_line(&mut line)?;
+            if line == "\r\n" || line.is_empty() {
+                break;
+            }
+            if let Some((name, value)) = line.split_once(':') {
+                let value = value.trim();
+                if name.eq_ignore_ascii_case("authorization") {
+                    authorization = Some(value.to_string());
+                } else if name.eq_ignore_ascii_case("content-length") {
+                    content_length = value.parse().unwrap_or(0);
+                }
+            }
+        }
+        let mut body = vec![0; content_length];
+        reader.read_exact(&mut body)?;
+
+        let auth = self
+            .authenticate(&method, &target, authorization.as_deref())
+            .map_err(|err| err.to_string());
+        let path = target.split('?').next().unwrap_or_default();
+        let (status, response) = match &auth {
+            Ok(_) => self
+                .routes
+                .lock()
+                .unwrap()
+                .get(&(method.to_uppercase(), path.to_string()))
+                .cloned()
+                .unwrap_or_else(|| {
+                    (404, error_body("No route matches the request"))
+                }),
+            Err(err) => (401, error_body(err)),
+        };
+
+        self.requests.lock().unwrap().push(ReceivedRequest {
+            method,
+            target,
+            body: String::from_utf8_lossy(&body).into_owned(),
+            auth,
+        });
+
+        let mut stream = stream;
+        write!(
+            stream,
+            "HTTP/1.1 {} X\r\nContent-Type: application/json\r\n\
+             Content-Length: {}\r\nConnection: close\r\n\r\n{}",
+            status,
+            response.len(),
+            response
+        )
+    }
+}
+
+fn error_body(message: &str) -> String {
+    serde_json::json!({ "message": message }).to_string()
+}
+
+/// Local HTTP server that authenticates requests the way an Atlassian
+/// product does, for end-to-end tests of client code.
+///
+/// Every request must have an `Authorization: JWT <token>` header with
+/// a token issued by the app and signed with its shared secret. The
+/// token's expiration time and query string hash are checked against
+/// the request. Requests that fail authentication get a 401 response
+/// with a JSON error message. Authenticated requests get the canned
+/// response registered with [`MockAtlassianServer::respond`], or a 404
+/// if there is none.
+///
+/// The server shuts down when dropped.
+pub struct MockAtlassianServer {
+    state: Arc<State>,
+    addr: String,
+}
+
+impl MockAtlassianServer {
+    /// Start a server that accepts tokens issued by `app_key` and
+    /// signed with `shared_secret`.
+    pub fn start<K: Into<String>>(
+        app_key: K,
+        shared_secret: SharedSecret,
+    ) -> io::Result<MockAtlassianServer> {
+        let listener = TcpListener::bind("127.0.0.1:0")?;
+        let addr = listener.local_addr()?.to_string();
+        let state = Arc::new(State {
+            url: Url::parse(&format!("http://{}/", addr))
+                .expect("invalid server address"),
+            app_key: app_key.into(),
+            verifier: Verifier::new(shared_secret),
+            routes: Mutex::new(HashMap::new()),
+            requests: Mutex::new(Vec::new()),
+            shutdown: AtomicBool::new(false),
+        });
+
+        let thread_state = state.clone();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                if thread_state.shutdown.load(Ordering::SeqCst) {
+                    break;
+                }
+                if let Ok(stream) = stream {
+                    // Errors only affect the one connection.
+                    let _ = thread_state.handle(stream);
+                }
+            }
+        });
+
+        Ok(MockAtlassianServer { state, addr })
+    }
+
+    /// Base URL of the server, e.g. `http://127.0.0.1:1234/`.
+    pub fn url(&self) -> &Url {
+        &self.state.url
+    }
+
+    /// Respond to authenticated `method` requests for `path` (which
+    /// must not include a query string) with `status` and a JSON
+    /// `body`.
+    pub fn respond<M, P, B>(&self, method: M, path: P, status: u16, body: B)
+    where
+        M: AsRef<str>,
+        P: Into<String>,
+        B: Into<String>,
+    {
+        self.state.routes.lock().unwrap().insert(
+            (method.as_ref().to_uppercase(), path.into()),
+            (status, body.into()),
+        );
+    }
+
+    /// Requests received so far, in order.
+    pub fn requests(&self) -> Vec<ReceivedRequest> {
+        self.state.requests.lock().unwrap().clone()
+    }
+}
+
+impl fmt::Debug for MockAtlassianServer {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("MockAtlassianServer")
+            .field("url", &self.state.url)
+            .field("app_key", &self.state.app_key)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Drop for MockAtlassianServer {
+    fn drop(&mut self) {
+        self.state.shutdown.store(true, Ordering::SeqCst);
+        // Wake up the server thread so that it sees the flag.
+        let _ = TcpStream::connect(&self.addr);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Signer;
+
+    #[test]
+    fn test_mock_server() {
+        let server =
+            MockAtlassianServer::start("app", SharedSecret::new("secret"))
+                .unwrap();
+        server.respond("GET", "/rest/api/3/myself", 200, r#"{"ok": true}"#);
+        let signer = Signer::new("app", SharedSecret::new("secret"));
+        let client = reqwest::blocking::Client::new();
+
+        let send = |url: Url, sign_url: &Url| {
+            let header = signer.create_auth_header("GET", sign_url).unwrap();
+            client
+                .get(url)
+                .header(header.name, header.value)
+                .send()
+                .unwrap()
+        };
+
+        let url = server.url().join("/rest/api/3/myself?a=1").unwrap();
+        let resp = send(url.clone(), &url);
+        assert_eq!(resp.status(), 200);
+        assert_eq!(resp.text().unwrap(), r#"{"ok": true}"#);
+
+        // Token for a different query
+        let other = server.url().join("/rest/api/3/myself?a=2").unwrap();
+        assert_eq!(send(url.clone(), &other).status(), 401);
+
+        // No route
+        let missing = server.url().join("/rest/api/3/missing").unwrap();
+        assert_eq!(send(missing.clone(), &missing).status(), 404);
+
+        let requests = server.requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].target, "/rest/api/3/myself?a=1");
+        assert_eq!(requests[0].auth, Ok("app".to_string()));
+        assert!(requests[1].auth.is_err());
+    }
+}
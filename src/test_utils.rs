@@ -0,0 +1,401 @@
+use crate::{
+    create_canonical_request, create_query_string_hash, hash_canonical_request,
+    AuthError, Claims, Clock, Credentials, SharedSecret, Signer, SystemClock,
+    TenantStore,
+};
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use url::Url;
+
+/// [`Clock`] whose time only changes when it is set or advanced, for
+/// tests that check exact `iat` and `exp` values or expiration
+/// without sleeping.
+///
+/// Share the clock through an `Arc` so that the test can advance it
+/// after passing it to a signer or verifier:
+///
+/// ```
+/// use atlassian_app_auth::{MockClock, SharedSecret, Signer};
+/// use std::sync::Arc;
+/// use std::time::Duration;
+///
+/// let clock = Arc::new(MockClock::from_unix_secs(1_600_000_000));
+/// let signer = Signer::new("app", SharedSecret::new("secret"))
+///     .with_clock(clock.clone());
+/// clock.advance(Duration::from_secs(60));
+/// ```
+#[derive(Debug)]
+pub struct MockClock {
+    now: Mutex<SystemTime>,
+}
+
+impl MockClock {
+    /// Create a new `MockClock` set to `now`.
+    pub fn new(now: SystemTime) -> MockClock {
+        MockClock {
+            now: Mutex::new(now),
+        }
+    }
+
+    /// Create a new `MockClock` set to a Unix timestamp.
+    pub fn from_unix_secs(secs: u64) -> MockClock {
+        MockClock::new(UNIX_EPOCH + Duration::from_secs(secs))
+    }
+
+    /// Set the current time.
+    pub fn set(&self, now: SystemTime) {
+        *self.now.lock().unwrap() = now;
+    }
+
+    /// Move the current time forward by `duration`.
+    pub fn advance(&self, duration: Duration) {
+        *self.now.lock().unwrap() += duration;
+    }
+}
+
+impl Clock for MockClock {
+    fn now(&self) -> SystemTime {
+        *self.now.lock().unwrap()
+    }
+}
+
+/// [`TenantStore`] that holds shared secrets in memory, for tests.
+///
+/// Unlike a plain `HashMap`, tenants can be added and removed through
+/// a shared reference, e.g. to simulate an installation lifecycle
+/// callback while a [`TenantAuth`] holds the store.
+///
+/// [`TenantAuth`]: crate::TenantAuth
+#[derive(Debug, Default)]
+pub struct InMemoryTenantStore {
+    tenants: RwLock<HashMap<String, SharedSecret>>,
+}
+
+impl InMemoryTenantStore {
+    /// Create an empty store.
+    pub fn new() -> InMemoryTenantStore {
+        InMemoryTenantStore::default()
+    }
+
+    /// Add a tenant to the store.
+    pub fn with_tenant<K: Into<String>>(
+        self,
+        client_key: K,
+        shared_secret: SharedSecret,
+    ) -> InMemoryTenantStore {
+        self.insert(client_key, shared_secret);
+        self
+    }
+
+    /// Add or replace a tenant.
+    pub fn insert<K: Into<String>>(
+        &self,
+        client_key: K,
+        shared_secret: SharedSecret,
+    ) {
+        self.tenants
+            .write()
+            .unwrap()
+            .insert(client_key.into(), shared_secret);
+    }
+
+    /// Remove a tenant, as if the app had been uninstalled.
+    pub fn remove(&self, client_key: &str) {
+        self.tenants.write().unwrap().remove(client_key);
+    }
+}
+
+impl TenantStore for InMemoryTenantStore {
+    fn shared_secret(
+        &self,
+        client_key: &str,
+    ) -> Result<Option<SharedSecret>, AuthError> {
+        Ok(self.tenants.read().unwrap().get(client_key).cloned())
+    }
+}
+
+/// Creates tokens as Atlassian would send them to an app, for testing
+/// verification code.
+///
+/// Besides valid tokens, the factory can create tokens that should be
+/// rejected in specific ways. Each method returns the encoded JWT
+/// without the "JWT " prefix.
+#[derive(Clone)]
+pub struct TokenFactory {
+    client_key: String,
+    shared_secret: SharedSecret,
+    valid_for: Duration,
+    clock: Arc<dyn Clock>,
+}
+
+impl TokenFactory {
+    /// Create a new `TokenFactory` for the tenant identified by
+    /// `client_key`.
+    pub fn new<K: Into<String>>(
+        client_key: K,
+        shared_secret: SharedSecret,
+    ) -> TokenFactory {
+        TokenFactory {
+            client_key: client_key.into(),
+            shared_secret,
+            valid_for: Signer::DEFAULT_VALID_FOR,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Set the duration that tokens will be valid for.
+    pub fn with_valid_for(mut self, valid_for: Duration) -> TokenFactory {
+        self.valid_for = valid_for;
+        self
+    }
+
+    /// Use `clock` to get the current time instead of [`SystemClock`].
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> TokenFactory {
+        self.clock = clock;
+        self
+    }
+
+    /// Create an [`InMemoryTenantStore`] containing the factory's
+    /// tenant.
+    pub fn store(&self) -> InMemoryTenantStore {
+        InMemoryTenantStore::new()
+            .with_tenant(self.client_key.clone(), self.shared_secret.clone())
+    }
+
+    fn token(
+        &self,
+        issuer: &str,
+        qsh: String,
+        offset: i64,
+        shared_secret: &SharedSecret,
+    ) -> String {
+        let now = crate::clock::unix_time(&*self.clock)
+            .expect("clock is before the Unix epoch");
+        let iat = now.saturating_add_signed(offset);
+        Claims {
+            iss: issuer.into(),
+            qsh,
+            iat,
+            exp: iat + self.valid_for.as_secs(),
+            nbf: None,
+            sub: None,
+        }
+        .into_token(shared_secret)
+        .expect("failed to encode token")
+        .token
+    }
+
+    /// Create a valid token for a request.
+    pub fn valid(&self, method: &str, url: &Url) -> String {
+        let qsh = create_query_string_hash(method, url);
+        self.token(&self.client_key, qsh, 0, &self.shared_secret)
+    }
+
+    /// Create a token that expired one second ago.
+    pub fn expired(&self, method: &str, url: &Url) -> String {
+        let qsh = create_query_string_hash(method, url);
+        let offset = -(self.valid_for.as_secs() as i64) - 1;
+        self.token(&self.client_key, qsh, offset, &self.shared_secret)
+    }
+
+    /// Create a token whose query string hash doesn't match the
+    /// request.
+    pub fn wrong_qsh(&self, method: &str, url: &Url) -> String {
+        let canonical_request = create_canonical_request(method, url);
+        let qsh = hash_canonical_request(&format!("{}&", canonical_request));
+        self.token(&self.client_key, qsh, 0, &self.shared_secret)
+    }
+
+    /// Create a token claiming to be from `issuer`, but signed with
+    /// the factory tenant's shared secret.
+    pub fn wrong_issuer(
+        &self,
+        issuer: &str,
+        method: &str,
+        url: &Url,
+    ) -> String {
+        let qsh = create_query_string_hash(method, url);
+        self.token(issuer, qsh, 0, &self.shared_secret)
+    }
+
+    /// Create a token signed with a different shared secret.
+    pub fn wrong_secret(&self, method: &str, url: &Url) -> String {
+        let qsh = create_query_string_hash(method, url);
+        let secret =
+            SharedSecret::new(format!("not-{}", self.shared_secret.expose()));
+        self.token(&self.client_key, qsh, 0, &secret)
+    }
+}
+
+impl fmt::Debug for TokenFactory {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("TokenFactory")
+            .field("client_key", &self.client_key)
+            .field("shared_secret", &self.shared_secret)
+            .field("valid_for", &self.valid_for)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Real Atlassian site for opt-in integration tests, configured with
+/// environment variables.
+///
+/// `ATLASSIAN_TEST_BASE_URL` is the site's base URL, such as
+/// `https://example.atlassian.net`, and the credentials of an app that
+/// is installed on the site are read with [`Credentials::from_env`].
+/// If the base URL is not set, [`TestInstance::from_env`] returns
+/// `None` so that the test can be skipped:
+///
+/// ```no_run
+/// use atlassian_app_auth::TestInstance;
+///
+/// let instance = match TestInstance::from_env() {
+///     Some(instance) => instance,
+///     None => return,
+/// };
+/// let url = instance.url("/rest/api/3/myself");
+/// let header = instance.signer().create_auth_header("GET", &url).unwrap();
+/// ```
+#[derive(Debug)]
+pub struct TestInstance {
+    base_url: Url,
+    credentials: Credentials,
+}
+
+impl TestInstance {
+    /// Environment variable containing the site's base URL.
+    pub const BASE_URL_VAR: &'static str = "ATLASSIAN_TEST_BASE_URL";
+
+    /// Load the instance from the environment, or return `None` if
+    /// [`TestInstance::BASE_URL_VAR`] is not set.
+    ///
+    /// Panics if the base URL is set but is not valid, or the
+    /// credentials are missing, since that's a misconfigured test
+    /// environment rather than a reason to skip.
+    pub fn from_env() -> Option<TestInstance> {
+        let base_url = env::var(TestInstance::BASE_URL_VAR).ok()?;
+        let base_url = Url::parse(&base_url).unwrap_or_else(|err| {
+            panic!("invalid {}: {}", TestInstance::BASE_URL_VAR, err)
+        });
+        let credentials = Credentials::from_env()
+            .unwrap_or_else(|err| panic!("invalid credentials: {}", err));
+        Some(TestInstance {
+            base_url,
+            credentials,
+        })
+    }
+
+    /// Base URL of the site.
+    pub fn base_url(&self) -> &Url {
+        &self.base_url
+    }
+
+    /// Credentials of the app.
+    pub fn credentials(&self) -> &Credentials {
+        &self.credentials
+    }
+
+    /// Create a [`Signer`] from the credentials.
+    pub fn signer(&self) -> Signer {
+        self.credentials.signer()
+    }
+
+    /// Get the URL of `path`, which can include a query string,
+    /// relative to the base URL.
+    ///
+    /// Panics if the URL is not valid.
+    pub fn url(&self, path: &str) -> Url {
+        self.base_url
+            .join(path)
+            .unwrap_or_else(|err| panic!("invalid path {}: {}", path, err))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[cfg(feature = "verify")]
+    use crate::{TenantAuth, VerificationFailure, Verifier};
+
+    #[test]
+    fn test_mock_clock() {
+        let clock = Arc::new(MockClock::from_unix_secs(1000));
+        let signer = Signer::new("app", SharedSecret::new("secret"))
+            .with_valid_for(Duration::from_secs(30))
+            .with_clock(clock.clone());
+        let url = Url::parse("https://example.com/example").unwrap();
+
+        let token = signer.create_token("GET", &url).unwrap();
+        assert_eq!(token.issued_at, UNIX_EPOCH + Duration::from_secs(1000));
+        assert_eq!(token.expires_at, UNIX_EPOCH + Duration::from_secs(1030));
+
+        clock.advance(Duration::from_secs(30));
+        assert_eq!(
+            signer.create_token("GET", &url).unwrap().issued_at,
+            UNIX_EPOCH + Duration::from_secs(1030)
+        );
+    }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn test_mock_clock_verify() {
+        let clock = Arc::new(MockClock::from_unix_secs(1000));
+        let signer = Signer::new("app", SharedSecret::new("secret"))
+            .with_valid_for(Duration::from_secs(30))
+            .with_clock(clock.clone());
+        let verifier = Verifier::new(SharedSecret::new("secret"))
+            .with_clock(clock.clone());
+        let url = Url::parse("https://example.com/example").unwrap();
+
+        let token = signer.create_token("GET", &url).unwrap();
+        clock.advance(Duration::from_secs(30));
+        verifier.verify(&token.token, "GET", &url).unwrap();
+        clock.advance(Duration::from_secs(1));
+        assert!(matches!(
+            verifier.verify(&token.token, "GET", &url),
+            Err(AuthError::VerificationFailed {
+                reason: VerificationFailure::Expired
+            })
+        ));
+    }
+
+    #[cfg(feature = "verify")]
+    #[test]
+    fn test_token_factory() {
+        let factory = TokenFactory::new("tenant", SharedSecret::new("secret"));
+        let auth = TenantAuth::new("app", factory.store());
+        let url = Url::parse("https://example.com/example?a=1").unwrap();
+
+        let verify = |token: String| auth.verify(&token, "GET", &url);
+        assert_eq!(verify(factory.valid("GET", &url)).unwrap(), "tenant");
+        assert!(matches!(
+            verify(factory.expired("GET", &url)),
+            Err(AuthError::VerificationFailed {
+                reason: VerificationFailure::Expired
+            })
+        ));
+        assert!(matches!(
+            verify(factory.wrong_qsh("GET", &url)),
+            Err(AuthError::QshMismatch { .. })
+        ));
+        assert!(matches!(
+            verify(factory.wrong_issuer("other", "GET", &url)),
+            Err(AuthError::UnknownTenant)
+        ));
+        assert!(matches!(
+            verify(factory.wrong_secret("GET", &url)),
+            Err(AuthError::VerificationFailed {
+                reason: VerificationFailure::InvalidSignature
+            })
+        ));
+
+        auth.store().remove("tenant");
+        assert!(matches!(
+            verify(factory.valid("GET", &url)),
+            Err(AuthError::UnknownTenant)
+        ));
+    }
+}
@@ -0,0 +1,145 @@
+use crate::{AuthError, Claims, Clock, IssuedToken, VerificationFailure};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// Claims of a Connect JWT, for checking a token's issuer, query string
+/// hash, and lifetime without going through jsonwebtoken.
+///
+/// Get one from a token created by this crate with
+/// [`IssuedToken::parsed`], or from an encoded token with
+/// [`ParsedToken::decode`]. This is intended for things like
+/// connection pools and caches that need to know whether a token is
+/// still fresh.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ParsedToken {
+    issuer: String,
+    qsh: String,
+    issued_at: SystemTime,
+    expires_at: SystemTime,
+    not_before: Option<SystemTime>,
+}
+
+fn unix_time(secs: u64) -> SystemTime {
+    UNIX_EPOCH + Duration::from_secs(secs)
+}
+
+impl ParsedToken {
+    /// Decode the claims of an encoded JWT.
+    ///
+    /// This does not check the token's signature, so don't trust the
+    /// claims of a token received from outside the app without
+    /// verifying it first.
+    pub fn decode(token: &str) -> Result<ParsedToken, AuthError> {
+        let claims = jsonwebtoken::dangerous_insecure_decode::<Claims>(token)
+            .map_err(|_| AuthError::VerificationFailed {
+                reason: VerificationFailure::Malformed,
+            })?
+            .claims;
+        Ok(ParsedToken {
+            issuer: claims.iss,
+            qsh: claims.qsh,
+            issued_at: unix_time(claims.iat),
+            expires_at: unix_time(claims.exp),
+            not_before: claims.nbf.map(unix_time),
+        })
+    }
+
+    /// Issuer of the token (the `iss` claim). For tokens created by
+    /// the app this is the app key, and for tokens sent by Atlassian
+    /// it's the tenant's client key.
+    pub fn issuer(&self) -> &str {
+        &self.issuer
+    }
+
+    /// Query string hash of the request the token was created for
+    /// (the `qsh` claim).
+    pub fn qsh(&self) -> &str {
+        &self.qsh
+    }
+
+    /// Time the token was issued (the `iat` claim).
+    pub fn issued_at(&self) -> SystemTime {
+        self.issued_at
+    }
+
+    /// Time the token expires (the `exp` claim).
+    pub fn expires_at(&self) -> SystemTime {
+        self.expires_at
+    }
+
+    /// Time before which the token must not be accepted (the `nbf`
+    /// claim), if set.
+    pub fn not_before(&self) -> Option<SystemTime> {
+        self.not_before
+    }
+
+    /// Time remaining until the token expires, according to `clock`,
+    /// or zero if it has already expired. Pass [`SystemClock`] to use
+    /// the system time.
+    ///
+    /// [`SystemClock`]: crate::SystemClock
+    pub fn expires_in(&self, clock: &dyn Clock) -> Duration {
+        self.expires_at
+            .duration_since(clock.now())
+            .unwrap_or_default()
+    }
+
+    /// Whether the token has expired, or will expire within `leeway`,
+    /// according to `clock`. A token that is about to be sent should
+    /// have enough leeway to reach the server before it expires.
+    pub fn is_expired(&self, clock: &dyn Clock, leeway: Duration) -> bool {
+        self.expires_in(clock) <= leeway
+    }
+}
+
+impl IssuedToken {
+    /// Get the claims of the token.
+    pub fn parsed(&self) -> ParsedToken {
+        ParsedToken::decode(&self.token).expect("issued token is invalid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{SharedSecret, Signer, SystemClock};
+    use url::Url;
+
+    #[test]
+    fn test_parsed_token() {
+        let now = UNIX_EPOCH + Duration::from_secs(1000);
+        let signer = Signer::new("app", SharedSecret::new("secret"))
+            .with_valid_for(Duration::from_secs(60));
+        let url = Url::parse("https://example.com/example?a=1").unwrap();
+        let issued = signer.create_token_at("GET", &url, now).unwrap();
+
+        let parsed = issued.parsed();
+        assert_eq!(parsed, ParsedToken::decode(&issued.token).unwrap());
+        assert_eq!(parsed.issuer(), "app");
+        assert_eq!(parsed.qsh(), crate::create_query_string_hash("GET", &url));
+        assert_eq!(parsed.issued_at(), issued.issued_at);
+        assert_eq!(parsed.expires_at(), issued.expires_at);
+        assert_eq!(parsed.not_before(), Some(now));
+
+        let at = |secs| move || UNIX_EPOCH + Duration::from_secs(secs);
+        assert_eq!(parsed.expires_in(&at(1000)), Duration::from_secs(60));
+        assert_eq!(parsed.expires_in(&at(1050)), Duration::from_secs(10));
+        assert_eq!(parsed.expires_in(&at(1090)), Duration::ZERO);
+        assert!(!parsed.is_expired(&at(1000), Duration::from_secs(10)));
+        assert!(parsed.is_expired(&at(1050), Duration::from_secs(10)));
+        // The token was issued in 1970, so it has long since expired.
+        assert_eq!(parsed.expires_in(&SystemClock), Duration::ZERO);
+        assert!(parsed.is_expired(&SystemClock, Duration::ZERO));
+
+        let fresh = signer.create_token("GET", &url).unwrap().parsed();
+        assert_eq!(fresh.not_before(), None);
+        assert!(!fresh.is_expired(&SystemClock, Duration::from_secs(10)));
+        assert!(fresh.is_expired(&SystemClock, Duration::from_secs(60)));
+
+        assert!(matches!(
+            ParsedToken::decode("not a token"),
+            Err(AuthError::VerificationFailed {
+                reason: VerificationFailure::Malformed
+            })
+        ));
+    }
+}
@@ -0,0 +1,279 @@
+use crate::{AuthError, QUERY_PARAM_ENCODE_SET};
+use percent_encoding::utf8_percent_encode;
+use url::Url;
+
+/// Base URL of the Atlassian API gateway.
+pub(crate) const GATEWAY_URL: &str = "https://api.atlassian.com";
+
+fn encode_query_value(value: &str) -> String {
+    utf8_percent_encode(value, QUERY_PARAM_ENCODE_SET).to_string()
+}
+
+/// Percent-encode a JQL query for use as the value of a `jql=` query
+/// parameter.
+///
+/// JQL's quotes, spaces, and operators are all encoded, using the same
+/// rules as the canonical request, so a URL built with the result
+/// produces the expected query string hash. Only the value is
+/// returned; prepend `jql=` yourself, or use [`UrlBuilder::jql`].
+///
+/// ```
+/// use atlassian_app_auth::encode_jql;
+///
+/// assert_eq!(
+///     encode_jql(r#"project = "MY PROJ" AND labels in (a, b)"#),
+///     "project%20%3D%20%22MY%20PROJ%22%20AND%20labels%20in%20%28a%2C%20b%29"
+/// );
+/// ```
+pub fn encode_jql(jql: &str) -> String {
+    encode_query_value(jql)
+}
+
+/// Builds request URLs for Atlassian REST APIs.
+///
+/// Query parameters are percent-encoded the same way as in the
+/// canonical request, so the URL that is sent has the same encoding
+/// as the URL that was signed. Path segments are percent-encoded as
+/// needed.
+///
+/// ```
+/// use atlassian_app_auth::UrlBuilder;
+/// use url::Url;
+///
+/// let base = Url::parse("https://example.atlassian.net").unwrap();
+/// let url = UrlBuilder::jira(&base)
+///     .path("search")
+///     .query("jql", "project = TEST AND status = \"In Progress\"")
+///     .query("fields", "summary,status")
+///     .build()
+///     .unwrap();
+/// assert_eq!(
+///     url.as_str(),
+///     "https://example.atlassian.net/rest/api/3/search\
+///      ?jql=project%20%3D%20TEST%20AND%20status%20%3D%20%22In%20Progress%22\
+///      &fields=summary%2Cstatus"
+/// );
+/// ```
+#[derive(Clone, Debug)]
+pub struct UrlBuilder {
+    base: Url,
+    api: Vec<String>,
+    segments: Vec<String>,
+    query: Vec<(String, String)>,
+}
+
+impl UrlBuilder {
+    /// Create a builder for paths relative to `base`, which can
+    /// include a context path such as `/wiki`.
+    pub fn new(base: &Url) -> UrlBuilder {
+        UrlBuilder {
+            base: base.clone(),
+            api: Vec::new(),
+            segments: Vec::new(),
+            query: Vec::new(),
+        }
+    }
+
+    /// Create a builder for the Jira Cloud platform REST API, version
+    /// 3 (`<base>/rest/api/3`).
+    pub fn jira(base: &Url) -> UrlBuilder {
+        UrlBuilder::new(base).api("rest/api/3")
+    }
+
+    /// Create a builder for the Confluence Cloud REST API
+    /// (`<base>/wiki/rest/api`). `base` is the site URL, without
+    /// `/wiki`.
+    pub fn confluence(base: &Url) -> UrlBuilder {
+        UrlBuilder::new(base).api("wiki/rest/api")
+    }
+
+    /// Create a builder for the `product` API (e.g. `jira` or
+    /// `confluence`) of the site identified by `cloud_id`, accessed
+    /// through the `api.atlassian.com` gateway. This is used for OAuth
+    /// 2.0 requests, which are authenticated with a bearer token (see
+    /// [`create_bearer_auth_header`]) rather than a JWT.
+    ///
+    /// [`create_bearer_auth_header`]: crate::create_bearer_auth_header
+    pub fn gateway(product: &str, cloud_id: &str) -> UrlBuilder {
+        let mut base =
+            Url::parse(GATEWAY_URL).expect("gateway URL is not valid");
+        base.path_segments_mut()
+            .expect("gateway URL cannot be a base")
+            .extend(&["ex", product, cloud_id]);
+        UrlBuilder::new(&base)
+    }
+
+    /// Create a builder for the Jira Cloud platform REST API, version
+    /// 3, through the gateway
+    /// (`https://api.atlassian.com/ex/jira/<cloud_id>/rest/api/3`).
+    pub fn jira_gateway(cloud_id: &str) -> UrlBuilder {
+        UrlBuilder::gateway("jira", cloud_id).api("rest/api/3")
+    }
+
+    /// Create a builder for the Confluence Cloud REST API through the
+    /// gateway
+    /// (`https://api.atlassian.com/ex/confluence/<cloud_id>/wiki/rest/api`).
+    pub fn confluence_gateway(cloud_id: &str) -> UrlBuilder {
+        UrlBuilder::gateway("confluence", cloud_id).api("wiki/rest/api")
+    }
+
+    /// Set the API prefix that comes between the base URL and the
+    /// resource path, e.g. `rest/api/2` or `rest/agile/1.0`.
+    pub fn api(mut self, prefix: &str) -> UrlBuilder {
+        self.api = split_path(prefix);
+        self
+    }
+
+    /// Append a resource path. The path is split on `/`, and each
+    /// segment is percent-encoded as needed.
+    pub fn path(mut self, path: &str) -> UrlBuilder {
+        self.segments.extend(split_path(path));
+        self
+    }
+
+    /// Append a single path segment. Unlike [`UrlBuilder::path`], a
+    /// `/` in `segment` is percent-encoded rather than starting a new
+    /// segment, so this is suitable for values such as issue keys.
+    pub fn segment(mut self, segment: &str) -> UrlBuilder {
+        self.segments.push(segment.into());
+        self
+    }
+
+    /// Append a query parameter. `key` and `value` should not already
+    /// be percent-encoded.
+    pub fn query(mut self, key: &str, value: &str) -> UrlBuilder {
+        self.query.push((key.into(), value.into()));
+        self
+    }
+
+    /// Append a `jql` query parameter. See [`encode_jql`].
+    pub fn jql(self, jql: &str) -> UrlBuilder {
+        self.query("jql", jql)
+    }
+
+    /// Build the URL.
+    pub fn build(&self) -> Result<Url, AuthError> {
+        let mut url = self.base.clone();
+        url.set_fragment(None);
+        url.path_segments_mut()
+            .map_err(|_| AuthError::InvalidUrl {
+                reason: "base URL cannot have a path",
+            })?
+            .pop_if_empty()
+            .extend(&self.api)
+            .extend(&self.segments);
+
+        if self.query.is_empty() {
+            url.set_query(None);
+        } else {
+            let query = self
+                .query
+                .iter()
+                .map(|(key, value)| {
+                    format!(
+                        "{}={}",
+                        encode_query_value(key),
+                        encode_query_value(value)
+                    )
+                })
+                .collect::<Vec<_>>()
+                .join("&");
+            url.set_query(Some(&query));
+        }
+        Ok(url)
+    }
+}
+
+fn split_path(path: &str) -> Vec<String> {
+    path.split('/')
+        .filter(|s| !s.is_empty())
+        .map(String::from)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::create_query_string_hash_from_parts;
+
+    #[test]
+    fn test_encode_jql() {
+        let jql = r#"summary ~ "foo&bar" ORDER BY created DESC"#;
+        assert_eq!(
+            encode_jql(jql),
+            "summary%20~%20%22foo%26bar%22%20ORDER%20BY%20created%20DESC"
+        );
+
+        let base = Url::parse("https://example.atlassian.net").unwrap();
+        let url = UrlBuilder::jira(&base)
+            .path("search")
+            .jql(jql)
+            .build()
+            .unwrap();
+        assert_eq!(
+            url.query(),
+            Some(format!("jql={}", encode_jql(jql)).as_str())
+        );
+    }
+
+    #[test]
+    fn test_url_builder() {
+        let base = Url::parse("https://example.atlassian.net/").unwrap();
+
+        let url = UrlBuilder::jira(&base)
+            .path("/issue/")
+            .segment("TEST/1")
+            .build()
+            .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://example.atlassian.net/rest/api/3/issue/TEST%2F1"
+        );
+
+        let url = UrlBuilder::confluence(&base)
+            .path("content")
+            .query("title", "a b+c")
+            .build()
+            .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://example.atlassian.net/wiki/rest/api/content\
+             ?title=a%20b%2Bc"
+        );
+
+        // The URL decodes back to the same parameters, so the qsh is
+        // the same as for the unencoded values.
+        let query = url.query_pairs().collect::<Vec<_>>();
+        assert_eq!(
+            create_query_string_hash_from_parts("GET", url.path(), &query),
+            create_query_string_hash_from_parts(
+                "GET",
+                "/wiki/rest/api/content",
+                &[("title", "a b+c")]
+            )
+        );
+
+        let base = Url::parse("https://example.com/prefix").unwrap();
+        let url = UrlBuilder::new(&base)
+            .api("rest/agile/1.0")
+            .path("board")
+            .build()
+            .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://example.com/prefix/rest/agile/1.0/board"
+        );
+
+        let url = UrlBuilder::jira_gateway("1234-abcd")
+            .path("myself")
+            .build()
+            .unwrap();
+        assert_eq!(
+            url.as_str(),
+            "https://api.atlassian.com/ex/jira/1234-abcd/rest/api/3/myself"
+        );
+
+        let base = Url::parse("mailto:user@example.com").unwrap();
+        assert!(UrlBuilder::new(&base).build().is_err());
+    }
+}
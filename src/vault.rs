@@ -0,0 +1,354 @@
+use crate::{
+    store_error, AuthError, Credentials, SharedSecret, Signer, TenantStore,
+};
+use serde::Deserialize;
+use serde_json::{Map, Value};
+use std::collections::HashMap;
+use std::env;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use url::Url;
+
+/// Version of the Vault KV secrets engine.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum VaultKvVersion {
+    /// Unversioned key/value store.
+    V1,
+    /// Versioned key/value store. This is the default.
+    V2,
+}
+
+/// Secret read from Vault.
+pub struct VaultSecret {
+    /// Key/value data of the secret.
+    pub data: Map<String, Value>,
+
+    /// How long the secret can be cached before it should be read
+    /// again. Vault returns zero for KV version 2 secrets.
+    pub lease_duration: Duration,
+}
+
+impl VaultSecret {
+    /// Get a string field of the secret.
+    pub fn field(&self, name: &str) -> Result<&str, AuthError> {
+        self.data
+            .get(name)
+            .and_then(Value::as_str)
+            .ok_or_else(|| store_error(format!("missing field {:?}", name)))
+    }
+}
+
+impl fmt::Debug for VaultSecret {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("VaultSecret")
+            .field("data", &self.data.keys().collect::<Vec<_>>())
+            .field("lease_duration", &self.lease_duration)
+            .finish()
+    }
+}
+
+#[derive(Deserialize)]
+struct ReadResponse {
+    lease_duration: u64,
+    data: Map<String, Value>,
+}
+
+/// Client for reading credentials from a HashiCorp Vault KV secrets
+/// engine.
+///
+/// This uses a blocking HTTP client, so it should not be called
+/// directly from async code.
+#[derive(Clone, Debug)]
+pub struct VaultClient {
+    http: reqwest::blocking::Client,
+    addr: Url,
+    token: SharedSecret,
+    mount: String,
+    kv_version: VaultKvVersion,
+}
+
+impl VaultClient {
+    /// Create a new `VaultClient`. `addr` is the base URL of the Vault
+    /// server, e.g. `https://vault.example.com:8200`. By default the
+    /// KV version 2 engine mounted at `secret` is used.
+    pub fn new(addr: Url, token: SharedSecret) -> VaultClient {
+        VaultClient {
+            http: reqwest::blocking::Client::new(),
+            addr,
+            token,
+            mount: "secret".into(),
+            kv_version: VaultKvVersion::V2,
+        }
+    }
+
+    /// Create a new `VaultClient` using the standard `VAULT_ADDR` and
+    /// `VAULT_TOKEN` environment variables.
+    pub fn from_env() -> Result<VaultClient, AuthError> {
+        let addr = env::var("VAULT_ADDR")
+            .map_err(|_| store_error("VAULT_ADDR is not set"))?;
+        let addr = Url::parse(&addr).map_err(store_error)?;
+        let token = env::var("VAULT_TOKEN")
+            .map_err(|_| store_error("VAULT_TOKEN is not set"))?;
+        Ok(VaultClient::new(addr, token.into()))
+    }
+
+    /// Set the mount path of the KV secrets engine.
+    pub fn with_mount<S: Into<String>>(mut self, mount: S) -> VaultClient {
+        self.mount = mount.into();
+        self
+    }
+
+    /// Set the version of the KV secrets engine.
+    pub fn with_kv_version(
+        mut self,
+        kv_version: VaultKvVersion,
+    ) -> VaultClient {
+        self.kv_version = kv_version;
+        self
+    }
+
+    fn secret_url(&self, path: &[&str]) -> Result<Url, AuthError> {
+        let mut url = self.addr.clone();
+        {
+            let mut segments = url
+                .path_segments_mut()
+                .map_err(|_| store_error("invalid Vault address"))?;
+            segments
+                .pop_if_empty()
+                .push("v1")
+                .extend(self.mount.split('/'));
+            if self.kv_version == VaultKvVersion::V2 {
+                segments.push("data");
+            }
+            segments.extend(path);
+        }
+        Ok(url)
+    }
+
+    fn read_segments(
+        &self,
+        path: &[&str],
+    ) -> Result<Option<VaultSecret>, AuthError> {
+        let resp = self
+            .http
+            .get(self.secret_url(path)?)
+            .header("X-Vault-Token", self.token.expose())
+            .send()
+            .map_err(store_error)?;
+        if resp.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        let resp: ReadResponse = resp
+            .error_for_status()
+            .and_then(|resp| resp.json())
+            .map_err(store_error)?;
+
+        let data = match self.kv_version {
+            VaultKvVersion::V1 => resp.data,
+            VaultKvVersion::V2 => match resp.data.get("data") {
+                Some(Value::Object(data)) => data.clone(),
+                _ => return Err(store_error("unexpected response format")),
+            },
+        };
+        Ok(Some(VaultSecret {
+            data,
+            lease_duration: Duration::from_secs(resp.lease_duration),
+        }))
+    }
+
+    /// Read the secret at `path`, or `None` if it doesn't exist.
+    pub fn read(&self, path: &str) -> Result<Option<VaultSecret>, AuthError> {
+        self.read_segments(&path.split('/').collect::<Vec<_>>())
+    }
+
+    /// Read [`Credentials`] from the secret at `path`.
+    pub fn credentials(&self, path: &str) -> Result<Credentials, AuthError> {
+        let secret = self
+            .read(path)?
+            .ok_or_else(|| store_error("secret not found"))?;
+        serde_json::from_value(Value::Object(secret.data))
+            .map_err(|_| store_error("secret is not valid credentials"))
+    }
+
+    /// Create a [`Signer`] from the secret at `path`, which must
+    /// contain [`Credentials`] fields.
+    pub fn signer(&self, path: &str) -> Result<Signer, AuthError> {
+        Ok(self.credentials(path)?.signer())
+    }
+}
+
+/// [`TenantStore`] backed by Vault.
+///
+/// The shared secret for each tenant is read from the secret at
+/// `<path_prefix>/<client_key>`. Secrets are cached for their lease
+/// duration, or for the default TTL if Vault doesn't return a lease
+/// duration (as is the case for KV version 2).
+pub struct VaultTenantStore {
+    client: VaultClient,
+    path_prefix: String,
+    field: String,
+    default_ttl: Duration,
+    cache: Mutex<HashMap<String, (SharedSecret, Instant)>>,
+}
+
+impl VaultTenantStore {
+    /// Default time to cache secrets that don't have a lease duration.
+    pub const DEFAULT_TTL: Duration = Duration::from_secs(300);
+
+    /// Create a new `VaultTenantStore`. Shared secrets are read from
+    /// the `shared_secret` field by default.
+    pub fn new<S: Into<String>>(
+        client: VaultClient,
+        path_prefix: S,
+    ) -> VaultTenantStore {
+        VaultTenantStore {
+            client,
+            path_prefix: path_prefix.into(),
+            field: "shared_secret".into(),
+            default_ttl: VaultTenantStore::DEFAULT_TTL,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Set the name of the field that holds the shared secret.
+    pub fn with_field<S: Into<String>>(mut self, field: S) -> VaultTenantStore {
+        self.field = field.into();
+        self
+    }
+
+    /// Set how long to cache secrets that don't have a lease duration.
+    pub fn with_default_ttl(mut self, ttl: Duration) -> VaultTenantStore {
+        self.default_ttl = ttl;
+        self
+    }
+}
+
+impl TenantStore for VaultTenantStore {
+    fn shared_secret(
+        &self,
+        client_key: &str,
+    ) -> Result<Option<SharedSecret>, AuthError> {
+        let now = Instant::now();
+        if let Some((secret, expires)) =
+            self.cache.lock().unwrap().get(client_key)
+        {
+            if now < *expires {
+                return Ok(Some(secret.clone()));
+            }
+        }
+
+        // The client key is a single path segment, so it gets escaped
+        // if it contains a slash.
+        let mut path = self
+            .path_prefix
+            .split('/')
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>();
+        path.push(client_key);
+        let vault_secret = match self.client.read_segments(&path)? {
+            Some(vault_secret) => vault_secret,
+            None => return Ok(None),
+        };
+        let secret = SharedSecret::new(vault_secret.field(&self.field)?);
+
+        let ttl = if vault_secret.lease_duration.is_zero() {
+            self.default_ttl
+        } else {
+            vault_secret.lease_duration
+        };
+        self.cache
+            .lock()
+            .unwrap()
+            .insert(client_key.into(), (secret.clone(), now + ttl));
+
+        Ok(Some(secret))
+    }
+}
+
+impl fmt::Debug for VaultTenantStore {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("VaultTenantStore")
+            .field("client", &self.client)
+            .field("path_prefix", &self.path_prefix)
+            .field("field", &self.field)
+            .field("default_ttl", &self.default_ttl)
+            .finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fake_server::serve;
+
+    fn kv2_response(data: &str) -> String {
+        format!(
+            r#"{{"lease_duration": 0, "data": {{"data": {}, "metadata": {{}}}}}}"#,
+            data
+        )
+    }
+
+    #[test]
+    fn test_signer() {
+        let server = serve(vec![(
+            200,
+            kv2_response(r#"{"key": "app", "secret": "secret"}"#),
+        )]);
+        let client = VaultClient::new(server.url.clone(), "token".into());
+
+        let signer = client.signer("atlassian/app").unwrap();
+        assert_eq!(signer.app_key(), "app");
+
+        let requests = server.requests.lock().unwrap();
+        assert!(requests[0]
+            .head
+            .starts_with("GET /v1/secret/data/atlassian/app HTTP/1.1"));
+        assert!(requests[0].head.contains("x-vault-token: token"));
+        assert!(requests[0].body.is_empty());
+    }
+
+    #[test]
+    fn test_tenant_store() {
+        let server = serve(vec![
+            (200, kv2_response(r#"{"shared_secret": "secret"}"#)),
+            (404, r#"{"errors": []}"#.into()),
+        ]);
+        let store = VaultTenantStore::new(
+            VaultClient::new(server.url.clone(), "token".into()),
+            "tenants",
+        );
+
+        // The second lookup is served from the cache.
+        for _ in 0..2 {
+            let secret = store.shared_secret("a/b").unwrap().unwrap();
+            assert_eq!(secret.expose(), "secret");
+        }
+        assert!(store.shared_secret("unknown").unwrap().is_none());
+
+        let requests = server.requests.lock().unwrap();
+        assert_eq!(requests.len(), 2);
+        assert!(requests[0]
+            .head
+            .starts_with("GET /v1/secret/data/tenants/a%2Fb HTTP/1.1"));
+    }
+
+    #[test]
+    fn test_kv_v1() {
+        let server = serve(vec![(
+            200,
+            r#"{"lease_duration": 60, "data": {"shared_secret": "secret"}}"#
+                .into(),
+        )]);
+        let client = VaultClient::new(server.url.clone(), "token".into())
+            .with_mount("kv")
+            .with_kv_version(VaultKvVersion::V1);
+
+        let secret = client.read("tenants/a").unwrap().unwrap();
+        assert_eq!(secret.field("shared_secret").unwrap(), "secret");
+        assert_eq!(secret.lease_duration, Duration::from_secs(60));
+        assert!(server.requests.lock().unwrap()[0]
+            .head
+            .starts_with("GET /v1/kv/tenants/a HTTP/1.1"));
+    }
+}
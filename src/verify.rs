@@ -0,0 +1,531 @@
+use crate::{
+    create_canonical_request, hash_canonical_request, instrument, AuthError,
+    AuthEvent, AuthEventKind, AuthEventSink, Claims, Clock, SharedSecret,
+    SystemClock, VerificationFailure,
+};
+use std::fmt;
+use std::sync::Arc;
+use subtle::ConstantTimeEq;
+use url::Url;
+
+/// Compare two byte strings in constant time (with respect to their
+/// contents), so that an attacker can't use response timing to guess
+/// a valid value one byte at a time.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.ct_eq(b).into()
+}
+
+/// Convert a decoding error from jsonwebtoken to an [`AuthError`].
+pub(crate) fn decode_error(err: jsonwebtoken::errors::Error) -> AuthError {
+    use jsonwebtoken::errors::ErrorKind;
+
+    let reason = match err.kind() {
+        ErrorKind::InvalidToken
+        | ErrorKind::Base64(_)
+        | ErrorKind::Json(_)
+        | ErrorKind::Utf8(_) => VerificationFailure::Malformed,
+        ErrorKind::InvalidSignature => VerificationFailure::InvalidSignature,
+        ErrorKind::InvalidAlgorithm => {
+            VerificationFailure::UnsupportedAlgorithm
+        }
+        ErrorKind::ExpiredSignature => VerificationFailure::Expired,
+        ErrorKind::ImmatureSignature => VerificationFailure::NotYetValid,
+        _ => return AuthError::JwtError(err),
+    };
+    AuthError::VerificationFailed { reason }
+}
+
+/// Get the issuer of a token without verifying it.
+pub(crate) fn unverified_issuer(token: &str) -> Result<String, AuthError> {
+    Ok(jsonwebtoken::dangerous_insecure_decode::<Claims>(token)
+        .map_err(decode_error)?
+        .claims
+        .iss)
+}
+
+/// The `qsh` claim of a context token, which is not tied to a request.
+const CONTEXT_QSH: &str = "context-qsh";
+
+/// What the `qsh` claim of a token must match.
+#[derive(Clone, Copy)]
+pub(crate) enum ExpectedQsh<'a> {
+    /// The query string hash of the request the token was sent with.
+    Request { method: &'a str, url: &'a Url },
+    /// [`CONTEXT_QSH`], for a context token.
+    Context,
+}
+
+pub(crate) fn decode_and_verify(
+    token: &str,
+    expected_qsh: ExpectedQsh,
+    shared_secret: &SharedSecret,
+    clock: &dyn Clock,
+) -> Result<Claims, AuthError> {
+    #[cfg(feature = "tracing")]
+    let _span = match expected_qsh {
+        ExpectedQsh::Request { method, url } => {
+            instrument::verify_span(method, url.path())
+        }
+        ExpectedQsh::Context => instrument::verify_context_span(),
+    }
+    .entered();
+
+    let result = check_claims(token, expected_qsh, shared_secret, clock);
+    match &result {
+        Ok(claims) => instrument::token_verified(&claims.iss),
+        Err(err) => instrument::verification_failed(err),
+    }
+    result
+}
+
+fn check_claims(
+    token: &str,
+    expected_qsh: ExpectedQsh,
+    shared_secret: &SharedSecret,
+    clock: &dyn Clock,
+) -> Result<Claims, AuthError> {
+    // The expiration time is checked below rather than by jsonwebtoken
+    // so that the clock can be overridden.
+    let validation = jsonwebtoken::Validation {
+        validate_exp: false,
+        ..jsonwebtoken::Validation::new(jsonwebtoken::Algorithm::HS256)
+    };
+    let data = jsonwebtoken::decode::<Claims>(
+        token,
+        &jsonwebtoken::DecodingKey::from_secret(
+            shared_secret.expose().as_bytes(),
+        ),
+        &validation,
+    )
+    .map_err(decode_error)?;
+
+    let now = crate::clock::unix_time(clock)?;
+    if data.claims.exp < now {
+        return Err(AuthError::VerificationFailed {
+            reason: VerificationFailure::Expired,
+        });
+    }
+    if data.claims.nbf.is_some_and(|nbf| nbf > now) {
+        return Err(AuthError::VerificationFailed {
+            reason: VerificationFailure::NotYetValid,
+        });
+    }
+
+    let (expected, canonical_request) = match expected_qsh {
+        ExpectedQsh::Request { method, url } => {
+            let canonical_request = create_canonical_request(method, url);
+            (
+                hash_canonical_request(&canonical_request),
+                canonical_request,
+            )
+        }
+        ExpectedQsh::Context => (CONTEXT_QSH.to_string(), String::new()),
+    };
+    if !constant_time_eq(data.claims.qsh.as_bytes(), expected.as_bytes()) {
+        return Err(AuthError::QshMismatch {
+            expected,
+            actual: data.claims.qsh,
+            canonical_request,
+        });
+    }
+
+    Ok(data.claims)
+}
+
+/// Claims of a context token verified by
+/// [`Verifier::verify_context_token`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct ContextClaims {
+    /// Client key of the tenant that issued the token.
+    pub iss: String,
+
+    /// Atlassian account ID of the user the token was issued for, or
+    /// `None` for anonymous users.
+    pub sub: Option<String>,
+
+    /// The time that the token was issued.
+    pub iat: u64,
+
+    /// Token expiration time.
+    pub exp: u64,
+}
+
+/// Verify a JWT sent by Atlassian.
+///
+/// This checks the token's signature and expiration time, as well as
+/// that the query string hash matches the `method` and `url` of the
+/// incoming request.
+pub fn verify_token(
+    token: &str,
+    method: &str,
+    url: &Url,
+    shared_secret: &SharedSecret,
+) -> Result<(), AuthError> {
+    decode_and_verify(
+        token,
+        ExpectedQsh::Request { method, url },
+        shared_secret,
+        &SystemClock,
+    )
+    .map(|_| ())
+}
+
+/// Verifies JWTs sent by Atlassian.
+///
+/// This does the same checks as [`verify_token`], but holds on to the
+/// shared secret and can optionally report each verification to an
+/// [`AuthEventSink`].
+#[derive(Clone)]
+pub struct Verifier {
+    shared_secret: SharedSecret,
+    event_sink: Option<Arc<dyn AuthEventSink>>,
+    clock: Arc<dyn Clock>,
+}
+
+impl Verifier {
+    /// Create a new `Verifier`.
+    pub fn new(shared_secret: SharedSecret) -> Verifier {
+        Verifier {
+            shared_secret,
+            event_sink: None,
+            clock: Arc::new(SystemClock),
+        }
+    }
+
+    /// Send an [`AuthEvent`] to `sink` every time a token is verified.
+    pub fn with_event_sink(mut self, sink: Arc<dyn AuthEventSink>) -> Verifier {
+        self.event_sink = Some(sink);
+        self
+    }
+
+    /// Use `clock` to get the current time instead of [`SystemClock`].
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Verifier {
+        self.clock = clock;
+        self
+    }
+
+    /// Verify a token. See [`verify_token`] for details.
+    pub fn verify(
+        &self,
+        token: &str,
+        method: &str,
+        url: &Url,
+    ) -> Result<(), AuthError> {
+        self.verify_claims(token, method, url).map(|_| ())
+    }
+
+    /// Verify a context token, returning its claims.
+    ///
+    /// Context tokens are created by Atlassian for the app's frontend,
+    /// e.g. with `AP.context.getToken()` in an iframe, and are then
+    /// sent to the app's backend. They aren't tied to a request, so
+    /// instead of a query string hash their `qsh` claim is
+    /// `context-qsh`; this checks for that value, along with the
+    /// token's signature and expiration time. Tokens for a particular
+    /// request are rejected with [`AuthError::QshMismatch`], as are
+    /// context tokens passed to [`Verifier::verify`].
+    ///
+    /// This is the first step of issuing the app's own session tokens
+    /// (see [`SessionTokens`]): the returned `iss` is the tenant's
+    /// client key and `sub` is the user's account ID.
+    ///
+    /// Events sent to the [`AuthEventSink`] have an empty method and
+    /// path, since there is no request to describe.
+    ///
+    /// [`SessionTokens`]: crate::SessionTokens
+    pub fn verify_context_token(
+        &self,
+        token: &str,
+    ) -> Result<ContextClaims, AuthError> {
+        let claims =
+            self.verify_with(token, ExpectedQsh::Context, "", None, "")?;
+        Ok(ContextClaims {
+            iss: claims.iss,
+            sub: claims.sub,
+            iat: claims.iat,
+            exp: claims.exp,
+        })
+    }
+
+    pub(crate) fn verify_claims(
+        &self,
+        token: &str,
+        method: &str,
+        url: &Url,
+    ) -> Result<Claims, AuthError> {
+        self.verify_with(
+            token,
+            ExpectedQsh::Request { method, url },
+            method,
+            url.host_str(),
+            url.path(),
+        )
+    }
+
+    /// Verify `token`, reporting the result to the event sink with
+    /// the given request details.
+    fn verify_with(
+        &self,
+        token: &str,
+        expected_qsh: ExpectedQsh,
+        method: &str,
+        host: Option<&str>,
+        path: &str,
+    ) -> Result<Claims, AuthError> {
+        let stopwatch = crate::clock::Stopwatch::start();
+        let result = decode_and_verify(
+            token,
+            expected_qsh,
+            &self.shared_secret,
+            &*self.clock,
+        );
+
+        if let Some(sink) = &self.event_sink {
+            // If verification failed, fall back to the unverified
+            // issuer so that the event can still be attributed.
+            let issuer = match &result {
+                Ok(claims) => Some(claims.iss.clone()),
+                Err(_) => unverified_issuer(token).ok(),
+            };
+            sink.record(
+                &AuthEvent::new(
+                    AuthEventKind::Verify,
+                    issuer.as_deref(),
+                    method,
+                    host,
+                    path,
+                    result.as_ref().map(|_| ()),
+                )
+                .with_elapsed(stopwatch.elapsed()),
+            );
+        }
+
+        result
+    }
+}
+
+impl fmt::Debug for Verifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Verifier")
+            .field("shared_secret", &self.shared_secret)
+            .field("event_sink", &self.event_sink.is_some())
+            .finish_non_exhaustive()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{create_auth_header, Parameters, Signer};
+    use std::sync::Mutex;
+    use std::time::{Duration, SystemTime};
+
+    fn create_token(url: &str, secret: &str) -> String {
+        let header = create_auth_header(&Parameters {
+            method: "get".into(),
+            url: Url::parse(url).unwrap(),
+            valid_for: Duration::from_secs(60),
+            app_key: "com.example.app".into(),
+            shared_secret: secret.into(),
+        })
+        .unwrap();
+        header.value.strip_prefix("JWT ").unwrap().to_string()
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq(b"abc", b"abc"));
+        assert!(!constant_time_eq(b"abc", b"abd"));
+        assert!(!constant_time_eq(b"abc", b"abcd"));
+    }
+
+    #[test]
+    fn test_jwt_query_param() {
+        // Atlassian sends the token of iframe and page loads in the
+        // `jwt` query parameter, which is not part of the qsh.
+        let token = create_token("https://example.com/page?a=1", "secret");
+        let url =
+            Url::parse(&format!("https://example.com/page?a=1&jwt={}", token))
+                .unwrap();
+        verify_token(&token, "GET", &url, &SharedSecret::new("secret"))
+            .unwrap();
+    }
+
+    #[test]
+    fn test_errors_redacted() {
+        let url = "https://example.com/example";
+        let token = create_token(url, "hunter2");
+        let url = Url::parse(url).unwrap();
+
+        let errors = [
+            verify_token(&token, "GET", &url, &SharedSecret::new("wrong")),
+            verify_token(&token, "POST", &url, &SharedSecret::new("hunter2")),
+            verify_token("garbage", "GET", &url, &SharedSecret::new("hunter2")),
+        ];
+        for err in errors {
+            let err = err.unwrap_err();
+            for output in [format!("{}", err), format!("{:?}", err)] {
+                assert!(!output.contains("hunter2"));
+                assert!(!output.contains(&token));
+            }
+        }
+    }
+
+    #[derive(Default)]
+    struct RecordingSink(Mutex<Vec<String>>);
+
+    impl AuthEventSink for RecordingSink {
+        fn record(&self, event: &AuthEvent) {
+            self.0.lock().unwrap().push(format!(
+                "{:?} {:?} {} {:?} {} {}",
+                event.kind,
+                event.tenant,
+                event.method,
+                event.host,
+                event.path,
+                event.outcome.is_ok()
+            ));
+        }
+    }
+
+    #[test]
+    fn test_event_sink() {
+        let url = "https://example.com/example?query=x";
+        let token = create_token(url, "secret");
+        let url = Url::parse(url).unwrap();
+        let sink = Arc::new(RecordingSink::default());
+
+        let verifier = Verifier::new(SharedSecret::new("secret"))
+            .with_event_sink(sink.clone());
+        verifier.verify(&token, "GET", &url).unwrap();
+        verifier.verify(&token, "POST", &url).unwrap_err();
+
+        let signer = Signer::new("app", SharedSecret::new("secret"))
+            .with_event_sink(sink.clone());
+        signer.create_auth_header("GET", &url).unwrap();
+
+        assert_eq!(
+            *sink.0.lock().unwrap(),
+            [
+                "Verify Some(\"com.example.app\") GET Some(\"example.com\") /example true",
+                "Verify Some(\"com.example.app\") POST Some(\"example.com\") /example false",
+                "Sign None GET Some(\"example.com\") /example true",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_expired() {
+        let url = "https://example.com/example";
+        let token = create_token(url, "secret");
+        let url = Url::parse(url).unwrap();
+        let verifier = Verifier::new(SharedSecret::new("secret"));
+
+        let later = SystemTime::now() + Duration::from_secs(61);
+        let err = verifier
+            .with_clock(Arc::new(move || later))
+            .verify(&token, "GET", &url)
+            .unwrap_err();
+        assert!(matches!(
+            err,
+            AuthError::VerificationFailed {
+                reason: VerificationFailure::Expired
+            }
+        ));
+    }
+
+    #[test]
+    fn test_verify_context_token() {
+        let secret = SharedSecret::new("secret");
+        let verifier = Verifier::new(secret.clone());
+        let claims = Claims {
+            iss: "client-key".into(),
+            qsh: CONTEXT_QSH.into(),
+            iat: 1000,
+            exp: 1060,
+            nbf: None,
+            sub: Some("account-id".into()),
+        };
+        // Claims::into_token doesn't encode `sub`.
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &claims,
+            &jsonwebtoken::EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap();
+        let at = |secs| {
+            Arc::new(move || SystemTime::UNIX_EPOCH + Duration::from_secs(secs))
+        };
+
+        assert_eq!(
+            verifier
+                .clone()
+                .with_clock(at(1030))
+                .verify_context_token(&token)
+                .unwrap(),
+            ContextClaims {
+                iss: "client-key".into(),
+                sub: Some("account-id".into()),
+                iat: 1000,
+                exp: 1060,
+            }
+        );
+        assert!(matches!(
+            verifier
+                .clone()
+                .with_clock(at(1061))
+                .verify_context_token(&token),
+            Err(AuthError::VerificationFailed {
+                reason: VerificationFailure::Expired
+            })
+        ));
+
+        // Context tokens can't be used for a request, and vice versa.
+        let url = Url::parse("https://example.com/example").unwrap();
+        assert!(matches!(
+            verifier
+                .clone()
+                .with_clock(at(1030))
+                .verify(&token, "GET", &url),
+            Err(AuthError::QshMismatch { .. })
+        ));
+        let request_token = create_token(url.as_str(), "secret");
+        assert!(matches!(
+            verifier.verify_context_token(&request_token),
+            Err(AuthError::QshMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_verify_token() {
+        let url = "https://example.com/example?query=x";
+        let token = create_token(url, "secret");
+        let secret = SharedSecret::new("secret");
+
+        verify_token(&token, "GET", &Url::parse(url).unwrap(), &secret)
+            .unwrap();
+
+        // Different query
+        let other = Url::parse("https://example.com/example?query=y").unwrap();
+        match verify_token(&token, "GET", &other, &secret) {
+            Err(AuthError::QshMismatch {
+                canonical_request, ..
+            }) => assert_eq!(canonical_request, "GET&/example&query=y"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+
+        // Wrong secret
+        assert!(matches!(
+            verify_token(
+                &token,
+                "GET",
+                &Url::parse(url).unwrap(),
+                &SharedSecret::new("wrong")
+            ),
+            Err(AuthError::VerificationFailed {
+                reason: VerificationFailure::InvalidSignature
+            })
+        ));
+    }
+}
@@ -0,0 +1,85 @@
+//! JavaScript bindings for Node and edge runtimes.
+//!
+//! Build with:
+//!
+//! ```text
+//! cargo rustc --lib --release --target wasm32-unknown-unknown \
+//!     --features wasm-bindings --crate-type cdylib
+//! wasm-bindgen --target nodejs --out-dir pkg \
+//!     target/wasm32-unknown-unknown/release/atlassian_app_auth.wasm
+//! ```
+//!
+//! The current time comes from JavaScript's `Date.now()`.
+//!
+//! Note that jsonwebtoken checks signatures with ring, whose
+//! constant-time comparison is implemented in C. On wasm32 that
+//! requires building ring with its `wasm32_c` feature (and a clang that
+//! can target wasm32).
+
+use crate::{AuthError, SharedSecret, Signer, Verifier};
+use std::sync::Arc;
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use url::Url;
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+extern "C" {
+    #[wasm_bindgen(js_namespace = Date, js_name = now)]
+    fn date_now() -> f64;
+}
+
+fn js_clock() -> SystemTime {
+    UNIX_EPOCH + Duration::from_millis(date_now() as u64)
+}
+
+fn to_js_error(err: AuthError) -> JsError {
+    JsError::new(&err.to_string())
+}
+
+fn parse_url(url: &str) -> Result<Url, JsError> {
+    Url::parse(url)
+        .map_err(|err| JsError::new(&format!("invalid URL: {}", err)))
+}
+
+/// Create the value of the Authorization header for a request, e.g.
+/// `"JWT eyJ0eXAi..."`.
+#[wasm_bindgen(js_name = createAuthHeader)]
+pub fn create_auth_header(
+    method: &str,
+    url: &str,
+    app_key: &str,
+    shared_secret: &str,
+    valid_for_secs: u32,
+) -> Result<String, JsError> {
+    let signer = Signer::new(app_key, SharedSecret::new(shared_secret))
+        .with_valid_for(Duration::from_secs(valid_for_secs.into()))
+        .with_clock(Arc::new(js_clock));
+    let header = signer
+        .create_auth_header(method, &parse_url(url)?)
+        .map_err(to_js_error)?;
+    Ok(header.value)
+}
+
+/// Create the query string hash ("qsh" claim) for a request.
+#[wasm_bindgen(js_name = createQueryStringHash)]
+pub fn create_query_string_hash(
+    method: &str,
+    url: &str,
+) -> Result<String, JsError> {
+    Ok(crate::create_query_string_hash(method, &parse_url(url)?))
+}
+
+/// Verify a JWT sent by Atlassian. Throws an error if the token is
+/// invalid.
+#[wasm_bindgen(js_name = verifyToken)]
+pub fn verify_token(
+    token: &str,
+    method: &str,
+    url: &str,
+    shared_secret: &str,
+) -> Result<(), JsError> {
+    Verifier::new(SharedSecret::new(shared_secret))
+        .with_clock(Arc::new(js_clock))
+        .verify(token, method, &parse_url(url)?)
+        .map_err(to_js_error)
+}
@@ -0,0 +1,310 @@
+use crate::client::SiteClient;
+use crate::{
+    AuthError, ClockDriftCheck, RateLimiter, RetryPolicy, Signer, UrlBuilder,
+};
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use url::Url;
+
+/// Maximum number of webhooks to request per page when listing.
+const PAGE_SIZE: usize = 100;
+
+/// Webhook to register with [`Webhooks::register`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct WebhookDetails {
+    /// JQL filter that issues must match to trigger the webhook.
+    pub jql_filter: String,
+
+    /// Events that trigger the webhook, e.g. `jira:issue_created`.
+    pub events: Vec<String>,
+
+    /// Only trigger `jira:issue_updated` events when one of these
+    /// fields changes.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub field_ids_filter: Option<Vec<String>>,
+
+    /// Only trigger issue property events for these property keys.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub issue_property_keys_filter: Option<Vec<String>>,
+}
+
+#[derive(Serialize)]
+struct Registration<'a> {
+    url: &'a str,
+    webhooks: &'a [WebhookDetails],
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RegistrationResponse {
+    webhook_registration_result: Vec<RegistrationResult>,
+}
+
+/// Result of registering one webhook with [`Webhooks::register`].
+#[derive(Clone, Debug, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct RegistrationResult {
+    /// ID of the new webhook, if it was created.
+    #[serde(default)]
+    pub created_webhook_id: Option<u64>,
+
+    /// Reasons the webhook was not created.
+    #[serde(default)]
+    pub errors: Vec<String>,
+}
+
+/// Webhook returned by [`Webhooks::list`].
+#[derive(Clone, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "camelCase")]
+#[non_exhaustive]
+pub struct Webhook {
+    /// Webhook ID.
+    pub id: u64,
+
+    /// Filter and events of the webhook.
+    #[serde(flatten)]
+    pub details: WebhookDetails,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Page<T> {
+    values: Vec<T>,
+    #[serde(default)]
+    is_last: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Deletion<'a> {
+    webhook_ids: &'a [u64],
+}
+
+/// Manages a Connect app's dynamic Jira webhooks, using the
+/// `/rest/api/3/webhook` endpoints.
+///
+/// Every request is signed with the app's [`Signer`], and failed
+/// requests are retried according to a [`RetryPolicy`]. This uses a
+/// blocking HTTP client, so it should not be called directly from
+/// async code.
+///
+/// ```no_run
+/// use atlassian_app_auth::{SharedSecret, Signer, WebhookDetails, Webhooks};
+/// use url::Url;
+///
+/// let signer = Signer::new("my-app", SharedSecret::new("secret"));
+/// let base = Url::parse("https://example.atlassian.net").unwrap();
+/// let webhooks = Webhooks::new(&base, signer);
+/// let results = webhooks
+///     .register(
+///         "/webhooks/issue-created",
+///         &[WebhookDetails {
+///             jql_filter: "project = TEST".into(),
+///             events: vec!["jira:issue_created".into()],
+///             ..Default::default()
+///         }],
+///     )
+///     .unwrap();
+/// ```
+#[derive(Debug)]
+pub struct Webhooks {
+    site: SiteClient,
+}
+
+impl Webhooks {
+    /// Create a new `Webhooks` for the site at `base`.
+    pub fn new(base: &Url, signer: Signer) -> Webhooks {
+        Webhooks {
+            site: SiteClient::new(base, signer),
+        }
+    }
+
+    /// Retry failed requests according to `retry_policy` instead of
+    /// [`RetryPolicy::default`].
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Webhooks {
+        self.site.options.retry_policy = retry_policy;
+        self
+    }
+
+    /// Take a token from `rate_limiter` before each request. The
+    /// site's base URL is used as the tenant.
+    pub fn with_rate_limiter(
+        mut self,
+        rate_limiter: Arc<RateLimiter>,
+    ) -> Webhooks {
+        self.site.options.rate_limiter = Some(rate_limiter);
+        self
+    }
+
+    /// Check the `Date` header of every response for clock drift with
+    /// `check`.
+    pub fn with_clock_drift_check(
+        mut self,
+        check: Arc<ClockDriftCheck>,
+    ) -> Webhooks {
+        self.site.options.clock_drift = Some(check);
+        self
+    }
+
+    /// Register `webhooks`, which will be sent to `url`. The URL is
+    /// relative to the app's base URL.
+    ///
+    /// Webhooks are registered individually, so the result for each
+    /// one is returned in the same order as `webhooks`.
+    pub fn register(
+        &self,
+        url: &str,
+        webhooks: &[WebhookDetails],
+    ) -> Result<Vec<RegistrationResult>, AuthError> {
+        let body = Registration { url, webhooks };
+        let response: RegistrationResponse =
+            self.site.send("POST", self.url().build()?, Some(&body))?;
+        Ok(response.webhook_registration_result)
+    }
+
+    /// Get all webhooks registered by the app.
+    pub fn list(&self) -> Result<Vec<Webhook>, AuthError> {
+        let mut webhooks = Vec::new();
+        loop {
+            let url = self
+                .url()
+                .query("startAt", &webhooks.len().to_string())
+                .query("maxResults", &PAGE_SIZE.to_string())
+                .build()?;
+            let page: Page<Webhook> =
+                self.site.send::<(), _>("GET", url, None)?;
+            let done = page.is_last || page.values.is_empty();
+            webhooks.extend(page.values);
+            if done {
+                return Ok(webhooks);
+            }
+        }
+    }
+
+    /// Delete the webhooks with IDs `ids`.
+    pub fn delete(&self, ids: &[u64]) -> Result<(), AuthError> {
+        let body = Deletion { webhook_ids: ids };
+        self.site
+            .execute("DELETE", self.url().build()?, Some(&body))?;
+        Ok(())
+    }
+
+    fn url(&self) -> UrlBuilder {
+        UrlBuilder::jira(self.site.base()).path("webhook")
+    }
+}
+
+#[cfg(all(test, feature = "verify"))]
+mod tests {
+    use super::*;
+    use crate::fake_server::serve;
+    use crate::{SharedSecret, Verifier};
+    use std::time::Duration;
+
+    #[test]
+    fn test_webhooks() {
+        let server = serve(vec![
+            (429, String::new()),
+            (
+                200,
+                r#"{"webhookRegistrationResult": [
+                    {"createdWebhookId": 1000},
+                    {"errors": ["Invalid JQL"]}
+                ]}"#
+                .into(),
+            ),
+            (
+                200,
+                r#"{"isLast": false, "values": [
+                    {"id": 1000, "jqlFilter": "project = A",
+                     "events": ["jira:issue_created"]}
+                ]}"#
+                .into(),
+            ),
+            (
+                200,
+                r#"{"isLast": true, "values": [
+                    {"id": 1001, "jqlFilter": "project = B",
+                     "events": ["jira:issue_updated"],
+                     "fieldIdsFilter": ["summary"]}
+                ]}"#
+                .into(),
+            ),
+            (202, String::new()),
+        ]);
+        let signer = Signer::new("app", SharedSecret::new("secret"));
+        let webhooks = Webhooks::new(&server.url, signer).with_retry_policy(
+            RetryPolicy::new().with_backoff(
+                Duration::from_millis(1),
+                Duration::from_millis(1),
+            ),
+        );
+
+        let details = WebhookDetails {
+            jql_filter: "project = A".into(),
+            events: vec!["jira:issue_created".into()],
+            ..Default::default()
+        };
+        let results = webhooks
+            .register("/hook", &[details.clone(), details.clone()])
+            .unwrap();
+        assert_eq!(results[0].created_webhook_id, Some(1000));
+        assert_eq!(results[1].errors, ["Invalid JQL"]);
+
+        let listed = webhooks.list().unwrap();
+        assert_eq!(listed.len(), 2);
+        assert_eq!(listed[0].details, details);
+        assert_eq!(
+            listed[1].details.field_ids_filter,
+            Some(vec!["summary".to_string()])
+        );
+
+        webhooks.delete(&[1000, 1001]).unwrap();
+
+        let requests = server.requests.lock().unwrap();
+        let lines = requests
+            .iter()
+            .map(|r| r.head.lines().next().unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            [
+                // The first attempt is rate limited and retried.
+                "POST /rest/api/3/webhook HTTP/1.1",
+                "POST /rest/api/3/webhook HTTP/1.1",
+                "GET /rest/api/3/webhook?startAt=0&maxResults=100 HTTP/1.1",
+                "GET /rest/api/3/webhook?startAt=1&maxResults=100 HTTP/1.1",
+                "DELETE /rest/api/3/webhook HTTP/1.1",
+            ]
+        );
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&requests[1].body)
+                .unwrap(),
+            serde_json::json!({
+                "url": "/hook",
+                "webhooks": [
+                    {"jqlFilter": "project = A",
+                     "events": ["jira:issue_created"]},
+                    {"jqlFilter": "project = A",
+                     "events": ["jira:issue_created"]},
+                ],
+            })
+        );
+        assert_eq!(requests[4].body, r#"{"webhookIds":[1000,1001]}"#);
+
+        // Each request is signed for its own method and query.
+        let verifier = Verifier::new(SharedSecret::new("secret"));
+        let token = requests[3]
+            .head
+            .lines()
+            .find_map(|line| line.strip_prefix("authorization: JWT "))
+            .unwrap();
+        let url = server
+            .url
+            .join("/rest/api/3/webhook?startAt=1&maxResults=100")
+            .unwrap();
+        verifier.verify(token, "GET", &url).unwrap();
+    }
+}
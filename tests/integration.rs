@@ -0,0 +1,115 @@
+//! End-to-end tests against a real Jira Cloud site, to catch
+//! differences between this crate's query string hash and Atlassian's.
+//!
+//! These are skipped unless `ATLASSIAN_TEST_BASE_URL`,
+//! `ATLASSIAN_APP_KEY`, and `ATLASSIAN_SHARED_SECRET` are set. The app
+//! must be installed on the site with at least the `READ` scope.
+//!
+//! ```sh
+//! cargo test --features test-utils --test integration
+//! ```
+
+use atlassian_app_auth::TestInstance;
+use reqwest::blocking::Client;
+
+/// Send a signed request for `path` and check that it succeeds.
+fn check(instance: &TestInstance, method: &str, path: &str, body: &str) {
+    let url = instance.url(path);
+    let header = instance
+        .signer()
+        .create_auth_header(method, &url)
+        .expect("failed to sign request");
+
+    let mut request = Client::new()
+        .request(method.parse().unwrap(), url)
+        .header(header.name, header.value);
+    if !body.is_empty() {
+        request = request
+            .header("Content-Type", "application/json")
+            .body(body.to_string());
+    }
+    let resp = request.send().expect("failed to send request");
+    let status = resp.status();
+    let text = resp.text().unwrap_or_default();
+    assert!(
+        status.is_success(),
+        "{} {} failed with {}: {}",
+        method,
+        path,
+        status,
+        text
+    );
+}
+
+macro_rules! instance {
+    () => {
+        match TestInstance::from_env() {
+            Some(instance) => instance,
+            None => {
+                eprintln!(
+                    "{} is not set, skipping",
+                    TestInstance::BASE_URL_VAR
+                );
+                return;
+            }
+        }
+    };
+}
+
+#[test]
+fn test_no_query() {
+    let instance = instance!();
+    check(&instance, "GET", "/rest/api/3/serverInfo", "");
+    check(&instance, "GET", "/rest/api/3/myself", "");
+}
+
+#[test]
+fn test_query() {
+    let instance = instance!();
+    // Parameters out of order, and one with an empty value.
+    check(
+        &instance,
+        "GET",
+        "/rest/api/3/project/search?orderBy=key&maxResults=1&query=",
+        "",
+    );
+    // Repeated parameters, which are hashed as one parameter with
+    // comma-separated values.
+    check(
+        &instance,
+        "GET",
+        "/rest/api/3/field/search?type=custom&type=system&maxResults=1",
+        "",
+    );
+}
+
+#[test]
+fn test_query_encoding() {
+    let instance = instance!();
+    // Spaces, quotes, reserved characters, and non-ASCII text in JQL.
+    check(
+        &instance,
+        "GET",
+        "/rest/api/3/search/jql?jql=text%20~%20%22caf%C3%A9%20%2B%26%3D%2A%22\
+         %20AND%20created%20%3E%3D%20-1d&fields=summary,status&maxResults=1",
+        "",
+    );
+    // `+` in the query decodes to a space, so it is hashed as `%20`.
+    check(
+        &instance,
+        "GET",
+        "/rest/api/3/user/search?query=a+b&maxResults=1",
+        "",
+    );
+}
+
+#[test]
+fn test_post() {
+    let instance = instance!();
+    check(
+        &instance,
+        "POST",
+        "/rest/api/3/jql/parse?validation=none",
+        r#"{"queries": ["created >= -1d"]}"#,
+    );
+}